@@ -44,6 +44,47 @@ impl<T> Array<T> {
         }
     }
 
+    /// Ensures there is room for `additional` more elements without growing.
+    pub fn reserve(&mut self, additional: usize) {
+        let required = self
+            .length
+            .checked_add(additional)
+            .expect("Capacity overflow");
+        while self.capacity < required {
+            self.grow();
+        }
+    }
+
+    /// Appends `value` without checking the capacity.
+    ///
+    /// # Safety
+    ///
+    /// There must be spare capacity, e.g. from a previous call to [`Array::reserve`].
+    pub unsafe fn push_unchecked(&mut self, value: T) {
+        debug_assert!(
+            self.length < self.capacity,
+            "push_unchecked without capacity"
+        );
+        unsafe { ptr::write(self.ptr.as_ptr().add(self.length), value) }
+        self.length += 1;
+    }
+
+    /// Removes and returns the element at `index`, shifting the rest down.
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(index < self.length, "Index out of bounds");
+        unsafe {
+            let slot = self.ptr.as_ptr().add(index);
+            let value = ptr::read(slot);
+            ptr::copy(slot.add(1), slot, self.length - index - 1);
+            self.length -= 1;
+            value
+        }
+    }
+
+    pub fn clear(&mut self) {
+        while self.pop().is_some() { /* drop all elements */ }
+    }
+
     pub fn length(&self) -> usize {
         self.length
     }
@@ -52,14 +93,23 @@ impl<T> Array<T> {
         self.length == 0
     }
 
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The capacity the array will have after it next grows.
+    pub fn grown_capacity(&self) -> usize {
+        if self.capacity == 0 {
+            1
+        } else {
+            self.capacity * 2
+        }
+    }
+
     fn grow(&mut self) {
         // This makes use of `Layout::array` which creates a memory layout matching a `[T; n]`.
-        let (new_capacity, new_layout) = if self.capacity == 0 {
-            (1, Layout::array::<T>(1))
-        } else {
-            let new_capacity = self.capacity * 2;
-            (new_capacity, Layout::array::<T>(new_capacity))
-        };
+        let new_capacity = self.grown_capacity();
+        let new_layout = Layout::array::<T>(new_capacity);
 
         // This `Layout` is used on `alloc` and `realloc`.
         let new_layout = new_layout.expect("Allocation too large");
@@ -117,6 +167,30 @@ impl<T> DerefMut for Array<T> {
     }
 }
 
+impl<T: Clone> Clone for Array<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: PartialEq> PartialEq for Array<T> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: Eq> Eq for Array<T> {}
+
+impl<T> FromIterator<T> for Array<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut array = Array::default();
+        for item in iter {
+            array.push(item);
+        }
+        array
+    }
+}
+
 impl io::Write for Array<u8> {
     fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
         for byte in bytes {
@@ -159,6 +233,55 @@ mod tests {
         assert_eq!(array.pop(), None);
     }
 
+    #[test]
+    fn test_reserve_and_push_unchecked() {
+        let mut array = Array::default();
+        array.push(0u64);
+        array.reserve(100);
+        for i in 1..=100 {
+            unsafe { array.push_unchecked(i) };
+        }
+        assert_eq!(array.length(), 101);
+        assert_eq!(array[100], 100);
+    }
+
+    #[test]
+    fn test_growth_doubles_capacity() {
+        let mut array = Array::default();
+        assert_eq!(array.grown_capacity(), 1);
+        for i in 0..5 {
+            array.push(i);
+        }
+        assert_eq!(array.capacity(), 8);
+        assert_eq!(array.grown_capacity(), 16);
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut array = Array::default();
+        array.push(String::from("a"));
+        array.push(String::from("b"));
+        array.clear();
+        assert!(array.is_empty());
+        array.push(String::from("c"));
+        assert_eq!(array[0], "c");
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut array: Array<String> = ["a", "b", "c"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(array.remove(1), "b");
+        assert_eq!(&*array, ["a", "c"]);
+        assert_eq!(array.remove(1), "c");
+        assert_eq!(array.clone(), array);
+    }
+
+    #[test]
+    fn test_collect() {
+        let array: Array<u32> = (1..=3).collect();
+        assert_eq!(&*array, &[1, 2, 3]);
+    }
+
     #[test]
     #[should_panic(expected = "Index out of bounds")]
     fn test_out_of_bounds() {
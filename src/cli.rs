@@ -0,0 +1,592 @@
+//! Command-line argument parsing for the `rox` binary.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::array::Array;
+use crate::compiler::lint::Lint;
+use crate::compiler::{CompileOptions, ErrorCode, ErrorFormat};
+use crate::vm::limits::Limits;
+
+pub const USAGE: &str = "\
+Usage: rox [options] [script.lox | script.roxc | -]
+       rox [options] <command> [command options]
+
+Commands:
+  run <script>                 Run a script (the default when given a path)
+  repl                         Start an interactive session (the default with no path)
+  compile <in.lox> [-o <out>]  Compile to a .roxc bytecode file
+  disasm [--format=<fmt>] <script>
+                               Disassemble a script; <fmt> is text, json or source
+  check [--lint] <script>      Parse a script without running it; --lint also warns
+                               about suspicious code
+  debug <script>               Run a script under the debugger
+  dap                          Serve the Debug Adapter Protocol over stdio
+  lsp                          Serve the Language Server Protocol over stdio
+  fmt [--check] <script>       Format a script in place; --check only reports
+  test <path>...               Run .lox files and compare against `// expect:` comments
+  explain [<code>]             Explain an error code, such as E0001, or list them all
+
+Options:
+  -e <source>                  Evaluate <source> instead of reading a script
+  --print-code                 Disassemble each chunk after compiling it
+  --trace-execution            Trace the stack and instructions while running
+  --max-stack=<n>              Allow the value stack to grow to <n> values (default 256)
+  --fuel=<n>                   Stop a run after <n> instructions
+  --max-memory=<bytes>         Stop a run that allocates more than <bytes>
+  --timeout=<ms>               Stop a run after <ms> milliseconds
+  --allow=<lint>[,<lint>...]   Silence lints, such as division-by-zero
+  --max-errors=<n>             Stop compiling after <n> errors
+  --warnings-as-errors         Fail when a lint warns
+  --error-format=<fmt>         Write diagnostics as human, json or github annotations
+  -h, --help                   Print this help
+  -V, --version                Print the version
+
+Use `-` as the script path to read from stdin.";
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Cli {
+    pub command: Command,
+    pub print_code: bool,
+    pub trace_execution: bool,
+    pub max_stack: Option<usize>,
+    pub limits: Limits,
+    pub compile_options: CompileOptions,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Help,
+    Version,
+    Repl,
+    Run {
+        input: Input,
+    },
+    Compile {
+        input: String,
+        output: Option<String>,
+    },
+    Disasm {
+        input: Input,
+        format: DisasmFormat,
+    },
+    Check {
+        input: Input,
+        lint: bool,
+        allow: Array<Lint>,
+    },
+    Debug {
+        input: Input,
+    },
+    Dap,
+    Lsp,
+    Fmt {
+        input: Input,
+        check: bool,
+    },
+    Test {
+        paths: Array<String>,
+    },
+    Explain {
+        code: Option<ErrorCode>,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Input {
+    File(String),
+    Stdin,
+    Inline(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisasmFormat {
+    Text,
+    Json,
+    Source,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct UsageError(pub String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n\n{USAGE}", self.0)
+    }
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "{path}"),
+            Self::Stdin => write!(f, "<stdin>"),
+            Self::Inline(_) => write!(f, "<-e>"),
+        }
+    }
+}
+
+fn usage_error(message: impl Into<String>) -> UsageError {
+    UsageError(message.into())
+}
+
+impl Cli {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, UsageError> {
+        let mut args = args.into_iter();
+        let mut cli = Cli {
+            command: Command::Repl,
+            print_code: false,
+            trace_execution: false,
+            max_stack: None,
+            limits: Limits::default(),
+            compile_options: CompileOptions::default(),
+        };
+        let mut inline = None;
+        let mut disasm_format = DisasmFormat::Text;
+        let mut check = false;
+        let mut lint = false;
+        let mut allow = Array::default();
+        let mut positionals: Array<String> = Array::default();
+
+        while let Some(arg) = args.next() {
+            // Nothing after the script path is an option for rox.
+            let script_started = positionals.first().is_some_and(|first| {
+                !is_subcommand(first) || (first == "run" && positionals.length() > 1)
+            });
+            if script_started {
+                positionals.push(arg);
+                continue;
+            }
+
+            match arg.as_str() {
+                "-h" | "--help" => cli.command = Command::Help,
+                "-V" | "--version" => cli.command = Command::Version,
+                "--print-code" => cli.print_code = true,
+                "--trace-execution" => cli.trace_execution = true,
+                "--check" => check = true,
+                "--lint" => lint = true,
+                "--warnings-as-errors" => cli.compile_options.warnings_as_errors = true,
+                "-e" => {
+                    let source = args
+                        .next()
+                        .ok_or_else(|| usage_error("Missing source after -e."))?;
+                    inline = Some(source);
+                }
+                "-o" => {
+                    let output = args
+                        .next()
+                        .ok_or_else(|| usage_error("Missing path after -o."))?;
+                    positionals.push(arg);
+                    positionals.push(output);
+                }
+                _ if arg.starts_with("--max-stack=") => {
+                    let value = &arg["--max-stack=".len()..];
+                    let max_stack = value
+                        .parse()
+                        .map_err(|_| usage_error(format!("Invalid stack size '{value}'.")))?;
+                    cli.max_stack = Some(max_stack);
+                }
+                _ if arg.starts_with("--fuel=") => {
+                    let value = &arg["--fuel=".len()..];
+                    let fuel = value
+                        .parse()
+                        .map_err(|_| usage_error(format!("Invalid fuel '{value}'.")))?;
+                    cli.limits.fuel = Some(fuel);
+                }
+                _ if arg.starts_with("--max-memory=") => {
+                    let value = &arg["--max-memory=".len()..];
+                    let max_memory = value
+                        .parse()
+                        .map_err(|_| usage_error(format!("Invalid memory size '{value}'.")))?;
+                    cli.limits.max_memory = Some(max_memory);
+                }
+                _ if arg.starts_with("--timeout=") => {
+                    let value = &arg["--timeout=".len()..];
+                    let millis = value
+                        .parse()
+                        .map_err(|_| usage_error(format!("Invalid timeout '{value}'.")))?;
+                    cli.limits.timeout = Some(Duration::from_millis(millis));
+                }
+                _ if arg.starts_with("--max-errors=") => {
+                    let value = &arg["--max-errors=".len()..];
+                    let max_errors = value
+                        .parse()
+                        .map_err(|_| usage_error(format!("Invalid error count '{value}'.")))?;
+                    cli.compile_options.max_errors = Some(max_errors);
+                }
+                _ if arg.starts_with("--error-format=") => {
+                    cli.compile_options.error_format = match &arg["--error-format=".len()..] {
+                        "human" => ErrorFormat::Human,
+                        "json" => ErrorFormat::Json,
+                        "github" => ErrorFormat::Github,
+                        other => {
+                            return Err(usage_error(format!("Unknown error format '{other}'.")));
+                        }
+                    };
+                }
+                _ if arg.starts_with("--allow=") => {
+                    for name in arg["--allow=".len()..].split(',') {
+                        let allowed = Lint::from_name(name)
+                            .ok_or_else(|| usage_error(format!("Unknown lint '{name}'.")))?;
+                        allow.push(allowed);
+                    }
+                }
+                _ if arg.starts_with("--format=") => {
+                    disasm_format = match &arg["--format=".len()..] {
+                        "text" => DisasmFormat::Text,
+                        "json" => DisasmFormat::Json,
+                        "source" => DisasmFormat::Source,
+                        other => return Err(usage_error(format!("Unknown format '{other}'."))),
+                    };
+                }
+                // The test suite passes its own flags, such as --chap17_compiling.
+                _ if arg.starts_with("--chap") => {}
+                _ if arg.starts_with('-') && arg != "-" => {
+                    return Err(usage_error(format!("Unknown option '{arg}'.")));
+                }
+                _ => positionals.push(arg),
+            }
+        }
+
+        if matches!(cli.command, Command::Help | Command::Version) {
+            return Ok(cli);
+        }
+
+        let mut positionals = positionals.iter().cloned();
+        let first = positionals.next();
+        let input = |path: Option<String>| -> Result<Input, UsageError> {
+            match (path, inline.clone()) {
+                (None, Some(source)) => Ok(Input::Inline(source)),
+                (Some(path), None) if path == "-" => Ok(Input::Stdin),
+                (Some(path), None) => Ok(Input::File(path)),
+                (Some(_), Some(_)) => Err(usage_error("Cannot use both -e and a script path.")),
+                (None, None) => Err(usage_error("Missing script path.")),
+            }
+        };
+
+        cli.command = match first.as_deref() {
+            None if inline.is_some() => Command::Run {
+                input: input(None)?,
+            },
+            None => Command::Repl,
+            Some("repl") => Command::Repl,
+            Some("run") => Command::Run {
+                input: input(positionals.next())?,
+            },
+            Some("compile") => {
+                let input = positionals
+                    .next()
+                    .ok_or_else(|| usage_error("Missing input path."))?;
+                let output = match (positionals.next().as_deref(), positionals.next()) {
+                    (None, _) => None,
+                    (Some("-o"), Some(output)) => Some(output),
+                    _ => return Err(usage_error("Expected `-o <output>`.")),
+                };
+                Command::Compile { input, output }
+            }
+            Some("disasm") => Command::Disasm {
+                input: input(positionals.next())?,
+                format: disasm_format,
+            },
+            Some("check") => Command::Check {
+                input: input(positionals.next())?,
+                lint,
+                allow,
+            },
+            Some("debug") => Command::Debug {
+                input: input(positionals.next())?,
+            },
+            Some("dap") => Command::Dap,
+            Some("lsp") => Command::Lsp,
+            Some("fmt") => Command::Fmt {
+                input: input(positionals.next())?,
+                check,
+            },
+            Some("test") => {
+                let paths: Array<String> = positionals.by_ref().collect();
+                if paths.is_empty() {
+                    return Err(usage_error("Missing test path."));
+                }
+                Command::Test { paths }
+            }
+            Some("explain") => {
+                let code = positionals
+                    .next()
+                    .map(|name| {
+                        ErrorCode::from_name(&name)
+                            .ok_or_else(|| usage_error(format!("Unknown error code '{name}'.")))
+                    })
+                    .transpose()?;
+                Command::Explain { code }
+            }
+            Some(path) => Command::Run {
+                input: input(Some(path.to_owned()))?,
+            },
+        };
+        // Lox has no variables to hold script arguments, so there is nothing
+        // to forward them to.
+        if let Some(extra) = positionals.next() {
+            return Err(usage_error(match cli.command {
+                Command::Run { .. } => {
+                    format!("Unexpected argument '{extra}'. Scripts do not take arguments.")
+                }
+                _ => format!("Unexpected argument '{extra}'."),
+            }));
+        }
+        Ok(cli)
+    }
+}
+
+fn is_subcommand(arg: &str) -> bool {
+    matches!(
+        arg,
+        "run"
+            | "repl"
+            | "compile"
+            | "disasm"
+            | "check"
+            | "debug"
+            | "dap"
+            | "lsp"
+            | "fmt"
+            | "test"
+            | "explain"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, UsageError> {
+        Cli::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn strings(args: &[&str]) -> Array<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn no_arguments_starts_repl() {
+        assert_eq!(parse(&[]).unwrap().command, Command::Repl);
+    }
+
+    #[test]
+    fn bare_path_runs_script() {
+        let cli = parse(&["--print-code", "script.lox"]).unwrap();
+        assert!(cli.print_code);
+        assert_eq!(
+            cli.command,
+            Command::Run {
+                input: Input::File("script.lox".into()),
+            }
+        );
+        assert_eq!(
+            parse(&["run", "-"]).unwrap().command,
+            Command::Run {
+                input: Input::Stdin,
+            }
+        );
+    }
+
+    #[test]
+    fn script_arguments_are_rejected() {
+        for args in [&["script.lox", "x"][..], &["run", "-", "a", "b"]] {
+            let UsageError(message) = parse(args).unwrap_err();
+            assert!(
+                message.contains("Scripts do not take arguments."),
+                "{message}"
+            );
+        }
+        let script_flag = parse(&["script.lox", "--trace-execution"]).unwrap_err();
+        assert!(script_flag.0.contains("'--trace-execution'"));
+        assert!(parse(&["dap", "x"]).is_err());
+    }
+
+    #[test]
+    fn test_suite_flags_are_ignored() {
+        let cli = parse(&["--chap17_compiling", "test.lox"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Run {
+                input: Input::File("test.lox".into()),
+            }
+        );
+        for typo in ["--max-eror=1", "--fule=10"] {
+            let UsageError(message) = parse(&[typo, "test.lox"]).unwrap_err();
+            assert!(
+                message.contains(&format!("Unknown option '{typo}'.")),
+                "{message}"
+            );
+        }
+    }
+
+    #[test]
+    fn inline_source() {
+        let cli = parse(&["-e", "1 + 2"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Run {
+                input: Input::Inline("1 + 2".into()),
+            }
+        );
+        assert_eq!(
+            parse(&["check", "-e", "1"]).unwrap().command,
+            Command::Check {
+                input: Input::Inline("1".into()),
+                lint: false,
+                allow: Array::default(),
+            }
+        );
+        assert!(parse(&["-e"]).is_err());
+        assert!(parse(&["-e", "1", "check", "a.lox"]).is_err());
+    }
+
+    #[test]
+    fn compile_with_output() {
+        assert_eq!(
+            parse(&["compile", "in.lox", "-o", "out.roxc"])
+                .unwrap()
+                .command,
+            Command::Compile {
+                input: "in.lox".into(),
+                output: Some("out.roxc".into()),
+            }
+        );
+        assert!(parse(&["compile"]).is_err());
+        assert!(parse(&["compile", "in.lox", "out.roxc"]).is_err());
+    }
+
+    #[test]
+    fn disasm_format() {
+        assert_eq!(
+            parse(&["disasm", "--format=json", "a.lox"])
+                .unwrap()
+                .command,
+            Command::Disasm {
+                input: Input::File("a.lox".into()),
+                format: DisasmFormat::Json,
+            }
+        );
+        assert!(parse(&["disasm", "--format=xml", "a.lox"]).is_err());
+    }
+
+    #[test]
+    fn help_and_version_win() {
+        assert_eq!(parse(&["check", "--help"]).unwrap().command, Command::Help);
+        assert_eq!(parse(&["-V"]).unwrap().command, Command::Version);
+    }
+
+    #[test]
+    fn debug() {
+        assert_eq!(
+            parse(&["debug", "a.lox"]).unwrap().command,
+            Command::Debug {
+                input: Input::File("a.lox".into())
+            }
+        );
+        assert_eq!(parse(&["dap"]).unwrap().command, Command::Dap);
+        assert_eq!(parse(&["lsp"]).unwrap().command, Command::Lsp);
+    }
+
+    #[test]
+    fn explain() {
+        assert_eq!(
+            parse(&["explain", "e0001"]).unwrap().command,
+            Command::Explain {
+                code: Some(ErrorCode::UnterminatedString)
+            }
+        );
+        assert_eq!(
+            parse(&["explain"]).unwrap().command,
+            Command::Explain { code: None }
+        );
+        assert!(parse(&["explain", "E9999"]).is_err());
+    }
+
+    #[test]
+    fn compile_options() {
+        let cli = parse(&[
+            "--max-errors=3",
+            "--warnings-as-errors",
+            "--error-format=github",
+            "a.lox",
+        ])
+        .unwrap();
+        assert_eq!(
+            cli.compile_options,
+            CompileOptions {
+                max_errors: NonZeroUsize::new(3),
+                warnings_as_errors: true,
+                error_format: ErrorFormat::Github,
+            }
+        );
+        assert!(parse(&["--max-errors=0", "a.lox"]).is_err());
+        assert!(parse(&["--error-format=xml", "a.lox"]).is_err());
+    }
+
+    #[test]
+    fn lint_flags() {
+        let cli = parse(&["check", "--lint", "--allow=division-by-zero", "a.lox"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Check {
+                input: Input::File("a.lox".into()),
+                lint: true,
+                allow: [Lint::DivisionByZero].into_iter().collect(),
+            }
+        );
+        assert!(parse(&["check", "--allow=everything", "a.lox"]).is_err());
+    }
+
+    #[test]
+    fn fmt_check() {
+        assert_eq!(
+            parse(&["fmt", "--check", "a.lox"]).unwrap().command,
+            Command::Fmt {
+                input: Input::File("a.lox".into()),
+                check: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_requires_paths() {
+        assert!(parse(&["test"]).is_err());
+        assert_eq!(
+            parse(&["test", "a", "b"]).unwrap().command,
+            Command::Test {
+                paths: strings(&["a", "b"])
+            }
+        );
+    }
+
+    #[test]
+    fn max_stack() {
+        assert_eq!(
+            parse(&["--max-stack=1024", "a.lox"]).unwrap().max_stack,
+            Some(1024)
+        );
+        assert!(parse(&["--max-stack=lots"]).is_err());
+    }
+
+    #[test]
+    fn limits() {
+        let cli = parse(&["--fuel=100", "--max-memory=4096", "--timeout=50", "a.lox"]).unwrap();
+        assert_eq!(
+            cli.limits,
+            Limits {
+                fuel: Some(100),
+                max_memory: Some(4096),
+                timeout: Some(Duration::from_millis(50)),
+            }
+        );
+        assert!(parse(&["--timeout=soon"]).is_err());
+    }
+
+    #[test]
+    fn unknown_short_option() {
+        assert!(parse(&["-z"]).is_err());
+    }
+}
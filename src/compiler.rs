@@ -1,14 +1,70 @@
+pub mod ast;
 pub mod codegen;
+mod diagnostic;
+mod error_code;
+pub mod lint;
 mod parser;
 pub mod scanner;
 
 use std::io;
+use std::num::NonZeroUsize;
 
 use crate::array::Array;
 
+pub use diagnostic::{Diagnostic, ErrorFormat, Severity, format_parse_errors};
+pub use error_code::ErrorCode;
 pub use parser::ParseError;
 use parser::Parser;
-use scanner::ScanError;
+use scanner::{ScanError, Scanner, TokenType};
+
+/// A byte range of the source, with the line and column it starts on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    /// Counted in characters from 1.
+    pub column: usize,
+}
+
+impl Span {
+    /// The span of `start..end` in `source`, which starts on `line`.
+    pub fn new(source: &str, start: usize, end: usize, line: usize) -> Self {
+        let line_start = source[..start].rfind('\n').map_or(0, |newline| newline + 1);
+        Self {
+            start,
+            end,
+            line,
+            column: source[line_start..start].chars().count() + 1,
+        }
+    }
+
+    /// The span of a token scanned from `source`.
+    pub fn of_token(source: &str, token: &scanner::Token<'_>) -> Self {
+        let start = scanner::offset_of(source, token);
+        Self::new(source, start, start + token.start.len(), token.line)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompileOptions {
+    /// Stop after this many errors; `None` reports them all.
+    pub max_errors: Option<NonZeroUsize>,
+    /// Whether lint warnings fail the compilation.
+    pub warnings_as_errors: bool,
+    pub error_format: ErrorFormat,
+}
+
+impl CompileOptions {
+    /// The severity warnings are reported with.
+    pub fn warning_severity(&self) -> Severity {
+        if self.warnings_as_errors {
+            Severity::Error
+        } else {
+            Severity::Warning
+        }
+    }
+}
 
 #[derive(Debug, thiserror::Error)]
 pub enum CompileError {
@@ -16,22 +72,19 @@ pub enum CompileError {
     Scan(#[from] ScanError),
     #[error(transparent)]
     Io(#[from] io::Error),
-    #[error("Parse error(s):\n{}", format_parse_errors(.0))]
+    #[error("Parse error(s):\n{}", format_parse_errors(.0, ErrorFormat::Human, ""))]
     Parse(Array<ParseError>),
 }
 
-fn format_parse_errors(errors: &Array<ParseError>) -> String {
-    errors.iter().fold(String::new(), |mut output, error| {
-        if !output.is_empty() {
-            output.push('\n');
-        }
-        output.push_str(&error.to_string());
-        output
-    })
+pub fn compile(source: &str) -> Result<crate::vm::chunk::Chunk, CompileError> {
+    compile_with(source, &CompileOptions::default())
 }
 
-pub fn compile(source: &str) -> Result<crate::vm::chunk::Chunk, CompileError> {
-    let parser = Parser::new(source);
+pub fn compile_with(
+    source: &str,
+    options: &CompileOptions,
+) -> Result<crate::vm::chunk::Chunk, CompileError> {
+    let parser = Parser::new(source, options);
     match parser.compile() {
         Ok(chunk) => Ok(chunk),
         Err(errors) => Err(CompileError::Parse(errors)),
@@ -39,10 +92,74 @@ pub fn compile(source: &str) -> Result<crate::vm::chunk::Chunk, CompileError> {
 }
 
 #[cfg(test)]
-mod tests {
+pub fn parse(source: &str) -> Result<ast::Program, CompileError> {
+    parse_with(source, &CompileOptions::default())
+}
+
+/// Parses `source` into a syntax tree without generating code.
+pub fn parse_with(source: &str, options: &CompileOptions) -> Result<ast::Program, CompileError> {
+    Parser::new(source, options)
+        .parse()
+        .map_err(CompileError::Parse)
+}
+
+/// Generates code from a syntax tree. For a tree from [`parse_with`], the
+/// chunk is identical to the one [`compile_with`] produces from the same
+/// source.
+pub fn generate(program: &ast::Program) -> Result<crate::vm::chunk::Chunk, CompileError> {
+    codegen::generate(program).map_err(|error| CompileError::Parse([error].into_iter().collect()))
+}
+
+/// Whether `source` is an unfinished prefix of a program: it has more opening
+/// than closing brackets, or ends inside a string literal or block comment.
+pub fn is_incomplete(source: &str) -> bool {
+    let mut scanner = Scanner::new(source);
+    let mut depth = 0isize;
+    while let Some(token) = scanner.scan_token() {
+        match token {
+            Ok(token) => match token.token_type {
+                TokenType::LeftParen | TokenType::LeftBrace => depth += 1,
+                TokenType::RightParen | TokenType::RightBrace => depth -= 1,
+                _ => {}
+            },
+            Err(err)
+                if matches!(
+                    err.code,
+                    ErrorCode::UnterminatedString | ErrorCode::UnterminatedComment
+                ) =>
+            {
+                return true;
+            }
+            Err(_) => {}
+        }
+    }
+    depth > 0
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use proptest::prelude::*;
+
     use crate::array::Array;
     use crate::vm::Vm;
 
+    /// Random well-formed expressions, spread over several lines.
+    pub(crate) fn expression() -> impl Strategy<Value = String> {
+        let leaf = (0u32..1000).prop_map(|n| n.to_string());
+        leaf.prop_recursive(6, 64, 2, |inner| {
+            prop_oneof![
+                inner.clone().prop_map(|e| format!("-{e}")),
+                inner.clone().prop_map(|e| format!("({e})")),
+                (
+                    inner.clone(),
+                    prop::sample::select(&["+", "-", "*", "/"][..]),
+                    inner
+                )
+                    .prop_map(|(l, op, r)| format!("{l} {op}\n{r}")),
+            ]
+        })
+    }
+
     fn run_capture(source: &str) -> Array<u8> {
         let mut vm = Vm::with_output(Array::default());
         vm.interpret(source).unwrap();
@@ -74,6 +191,19 @@ mod tests {
         assert_eq!(&*run_capture("8 / 2 * 4"), b"16\n");
     }
 
+    #[test]
+    fn incomplete_input() {
+        use super::is_incomplete;
+
+        assert!(is_incomplete("(1 + "));
+        assert!(is_incomplete("{ (1)"));
+        assert!(is_incomplete("\"unterminated"));
+        assert!(is_incomplete("1 /* open /* */"));
+        assert!(!is_incomplete("(1 + 2)"));
+        assert!(!is_incomplete("1 +"));
+        assert!(!is_incomplete("1)"));
+    }
+
     #[test]
     fn parse_error_carries_line_and_message() {
         let mut vm = Vm::with_output(Array::default());
@@ -83,10 +213,200 @@ mod tests {
                 assert!(!report.0.is_empty());
                 let e = &report.0[0];
                 assert_eq!(e.line, 1);
-                assert!(e.message.contains("Expect expression"));
+                assert_eq!(e.code, super::ErrorCode::ExpectExpression);
                 assert!(e.to_string().starts_with("[line 1] Error"));
             }
             other => panic!("expected Parse error, got {other:?}"),
         }
     }
+
+    fn parse_errors(source: &str) -> Vec<(usize, &'static str)> {
+        match super::compile(source) {
+            Err(super::CompileError::Parse(errors)) => {
+                errors.iter().map(|e| (e.line, e.message())).collect()
+            }
+            other => panic!("expected parse errors, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn reports_every_independent_error() {
+        assert_eq!(
+            parse_errors("1 +;\n2 *;\n(3"),
+            [
+                (1, "Expect expression."),
+                (2, "Expect expression."),
+                (3, "Expect ')' after expression."),
+            ]
+        );
+        assert_eq!(
+            parse_errors("print 1 +\nvar x = 2;\nreturn (1;"),
+            [
+                (1, "Expect expression."),
+                (2, "Expect expression."),
+                (3, "Expect ')' after expression."),
+            ]
+        );
+    }
+
+    #[test]
+    fn one_error_per_statement() {
+        assert_eq!(parse_errors("1 2 3"), [(1, "Expect end of expression.")]);
+        assert_eq!(
+            parse_errors("(1 + ) * (2 +);\n@ 3"),
+            [(1, "Expect expression."), (2, "Unexpected character.")]
+        );
+    }
+
+    #[test]
+    fn error_limit_stops_compilation() {
+        use std::num::NonZeroUsize;
+
+        use super::CompileOptions;
+
+        let options = CompileOptions {
+            max_errors: NonZeroUsize::new(2),
+            ..CompileOptions::default()
+        };
+        match super::compile_with("1 +;\n2 *;\n3 /;", &options) {
+            Err(super::CompileError::Parse(errors)) => {
+                let lines: Vec<usize> = errors.iter().map(|e| e.line).collect();
+                assert_eq!(lines, [1, 2]);
+            }
+            other => panic!("expected parse errors, got {other:?}"),
+        }
+
+        // Errors past the limit are not reported, but still fail the compile.
+        let options = CompileOptions {
+            max_errors: NonZeroUsize::new(1),
+            ..CompileOptions::default()
+        };
+        for source in ["1 2", "(1 +;\n2 @", "@ 1 2"] {
+            match super::compile_with(source, &options) {
+                Err(super::CompileError::Parse(errors)) => assert_eq!(errors.length(), 1),
+                other => panic!("expected parse errors for {source:?}, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn error_formats() {
+        use super::{CompileError, CompileOptions, Diagnostic, ErrorFormat, Severity};
+
+        let options = CompileOptions {
+            error_format: ErrorFormat::Json,
+            ..CompileOptions::default()
+        };
+        let Err(CompileError::Parse(errors)) = super::compile_with("1 +\n  )", &options) else {
+            panic!("expected a parse error");
+        };
+        let diagnostic = Diagnostic::from(&errors[0]);
+        assert_eq!(
+            diagnostic.render(ErrorFormat::Human, "a,b.lox"),
+            "[line 2] Error: Expect expression. [E0003]"
+        );
+        assert_eq!(
+            diagnostic.render(ErrorFormat::Json, "a,b.lox"),
+            r#"{"severity":"error","code":"E0003","message":"Expect expression.","file":"a,b.lox","line":2,"column":3,"span":{"start":6,"end":7},"trace":[]}"#
+        );
+        assert_eq!(
+            diagnostic.render(ErrorFormat::Github, "a,b.lox"),
+            "::error file=a%2Cb.lox,line=2,col=3::Expect expression. [E0003]"
+        );
+
+        let runtime = Diagnostic {
+            line: Some(4),
+            trace: [("script".to_owned(), 4)].into_iter().collect(),
+            ..Diagnostic::new(Severity::Error, "Bad: 100%")
+        };
+        assert_eq!(
+            runtime.render(ErrorFormat::Json, "-"),
+            r#"{"severity":"error","code":null,"message":"Bad: 100%","file":"-","line":4,"column":null,"span":null,"trace":[{"function":"script","line":4}]}"#
+        );
+        assert_eq!(
+            runtime.render(ErrorFormat::Github, "-"),
+            "::error file=-,line=4::Bad: 100%25"
+        );
+    }
+
+    /// Compiles `source` both ways and checks the chunks are identical.
+    fn assert_same_code(source: &str) {
+        let direct = super::compile(source).unwrap();
+        let via_ast = super::generate(&super::parse(source).unwrap()).unwrap();
+        assert_eq!(via_ast.codes, direct.codes, "codes for {source:?}");
+        assert_eq!(via_ast.lines, direct.lines, "lines for {source:?}");
+        assert_eq!(
+            via_ast.constants, direct.constants,
+            "constants for {source:?}"
+        );
+        assert_eq!(via_ast.max_stack_depth, direct.max_stack_depth);
+    }
+
+    #[test]
+    fn ast_has_spans() {
+        use super::ast::{BinaryOp, ExprKind, Stmt, UnaryOp};
+
+        let source = "-(1 +\n 2)";
+        let program = super::parse(source).unwrap();
+        let Stmt::Expression(negation) = &program.statements[0];
+        assert_eq!(&source[negation.span.start..negation.span.end], source);
+        assert_eq!((negation.span.line, negation.end_line), (1, 2));
+        let ExprKind::Unary { operator, operand } = &negation.kind else {
+            panic!("expected a negation, got {negation:?}");
+        };
+        assert_eq!(*operator, UnaryOp::Negate);
+        let ExprKind::Grouping(sum) = &operand.kind else {
+            panic!("expected a grouping, got {operand:?}");
+        };
+        let ExprKind::Binary { operator, left, .. } = &sum.kind else {
+            panic!("expected a sum, got {sum:?}");
+        };
+        assert_eq!(*operator, BinaryOp::Add);
+        assert_eq!(left.kind, ExprKind::Number(1.0));
+        assert_eq!(&source[sum.span.start..sum.span.end], "1 +\n 2");
+    }
+
+    #[test]
+    fn ast_parse_reports_errors() {
+        assert!(super::parse("(1 +").is_err());
+        assert!(super::parse("1 2").is_err());
+    }
+
+    #[test]
+    fn ast_codegen_matches_single_pass() {
+        for source in [
+            "1",
+            "-1",
+            "(5 - (3 - 1)) + -1",
+            "--(2)\n*\n3",
+            "8 / 2 * 4\n\n",
+        ] {
+            assert_same_code(source);
+        }
+    }
+
+    #[test]
+    fn ast_codegen_reports_too_many_constants() {
+        let source = (0..300)
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join(" + ");
+        let direct = super::compile(&source).unwrap_err().to_string();
+        let program = super::parse(&source).unwrap();
+        let via_ast = super::generate(&program).unwrap_err().to_string();
+        assert_eq!(via_ast, direct);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_compiled_stack_depth_matches_verifier(source in expression()) {
+            let chunk = super::compile(&source).unwrap();
+            prop_assert_eq!(chunk.max_stack_depth, chunk.stack_depth().unwrap());
+        }
+
+        #[test]
+        fn prop_ast_codegen_matches_single_pass(source in expression()) {
+            assert_same_code(&source);
+        }
+    }
 }
@@ -0,0 +1,56 @@
+//! A typed syntax tree, built by [`super::parse`] instead of emitting code.
+//!
+//! It mirrors the grammar the compiler accepts: a script is a single
+//! expression, whose value is printed when it returns.
+
+use crate::array::Array;
+
+use super::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub statements: Array<Stmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    /// An expression whose value the script returns.
+    Expression(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+    /// The line of the last token, where the single-pass compiler emits the
+    /// instruction that completes the expression.
+    pub end_line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Number(f64),
+    Grouping(Box<Expr>),
+    Unary {
+        operator: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Binary {
+        operator: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Negate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
@@ -1,3 +1,5 @@
+use super::ast::{BinaryOp, Expr, ExprKind, Program, Stmt, UnaryOp};
+use super::{ErrorCode, ParseError};
 use crate::vm::chunk::{Chunk, ExceededConstantCount};
 use crate::vm::opcode::OpCode;
 use crate::vm::value::Value;
@@ -6,15 +8,69 @@ pub fn emit_byte(chunk: &mut Chunk, byte: u8, line: usize) {
     chunk.write_byte(byte, line);
 }
 
-pub fn emit_bytes(chunk: &mut Chunk, b1: u8, b2: u8, line: usize) {
-    emit_byte(chunk, b1, line);
-    emit_byte(chunk, b2, line);
+pub fn emit_opcode(chunk: &mut Chunk, opcode: OpCode, line: usize) {
+    chunk.write_opcode(opcode, line);
 }
 
 pub fn emit_return(chunk: &mut Chunk, line: usize) {
-    emit_byte(chunk, OpCode::Return as u8, line);
+    emit_opcode(chunk, OpCode::Return, line);
 }
 
 pub fn make_constant(chunk: &mut Chunk, value: Value) -> Result<u8, ExceededConstantCount> {
     chunk.write_constant(value)
 }
+
+/// Generates code for `program`, exactly as the single-pass compiler would
+/// for the same source.
+pub fn generate(program: &Program) -> Result<Chunk, ParseError> {
+    let mut chunk = Chunk::default();
+    let mut line = 1;
+    for statement in program.statements.iter() {
+        match statement {
+            Stmt::Expression(expression) => {
+                generate_expression(&mut chunk, expression)?;
+                line = expression.end_line;
+            }
+        }
+    }
+    emit_return(&mut chunk, line);
+    Ok(chunk)
+}
+
+fn generate_expression(chunk: &mut Chunk, expression: &Expr) -> Result<(), ParseError> {
+    let line = expression.end_line;
+    match &expression.kind {
+        ExprKind::Number(value) => {
+            let index = make_constant(chunk, (*value).into()).map_err(|_| ParseError {
+                line,
+                code: ErrorCode::TooManyConstants,
+                span: expression.span,
+            })?;
+            emit_opcode(chunk, OpCode::Constant, line);
+            emit_byte(chunk, index, line);
+        }
+        ExprKind::Grouping(inner) => generate_expression(chunk, inner)?,
+        ExprKind::Unary { operator, operand } => {
+            generate_expression(chunk, operand)?;
+            match operator {
+                UnaryOp::Negate => emit_opcode(chunk, OpCode::Negate, line),
+            }
+        }
+        ExprKind::Binary {
+            operator,
+            left,
+            right,
+        } => {
+            generate_expression(chunk, left)?;
+            generate_expression(chunk, right)?;
+            let opcode = match operator {
+                BinaryOp::Add => OpCode::Add,
+                BinaryOp::Subtract => OpCode::Subtract,
+                BinaryOp::Multiply => OpCode::Multiply,
+                BinaryOp::Divide => OpCode::Divide,
+            };
+            emit_opcode(chunk, opcode, line);
+        }
+    }
+    Ok(())
+}
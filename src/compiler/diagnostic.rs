@@ -0,0 +1,190 @@
+//! Rendering compile and runtime errors for people and for tools.
+
+use crate::array::Array;
+use crate::json::Json;
+
+use super::lint::Warning;
+use super::scanner::ScanError;
+use super::{ParseError, Span};
+
+/// How serious a diagnostic is: errors stop compilation, warnings do not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn label(self) -> &'static str {
+        match self {
+            Severity::Error => "Error",
+            Severity::Warning => "Warning",
+        }
+    }
+
+    fn level(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// How diagnostics are written for their reader.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// `[line 1] Error: ...`, for people.
+    #[default]
+    Human,
+    /// One JSON object per line.
+    Json,
+    /// GitHub Actions workflow commands, such as `::error file=a.lox,line=1::...`.
+    Github,
+}
+
+/// One error or warning, with as much of its location as is known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The error code, or the lint that produced a warning.
+    pub code: Option<String>,
+    pub message: String,
+    pub line: Option<usize>,
+    pub span: Option<Span>,
+    /// The call stack of a runtime error, innermost frame first, as
+    /// function names and lines.
+    pub trace: Array<(String, usize)>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.into(),
+            line: None,
+            span: None,
+            trace: Array::default(),
+        }
+    }
+
+    /// Renders the diagnostic from `file` in `format`, on one line.
+    pub fn render(&self, format: ErrorFormat, file: &str) -> String {
+        let level = self.severity.level();
+        let line = self.line.or(self.span.map(|span| span.line));
+        let column = self.span.map(|span| span.column);
+        match format {
+            ErrorFormat::Human => {
+                let label = self.severity.label();
+                let code = self
+                    .code
+                    .as_ref()
+                    .map_or(String::new(), |code| format!(" [{code}]"));
+                match line {
+                    Some(line) => format!("[line {line}] {label}: {}{code}", self.message),
+                    None => format!("{label}: {}{code}", self.message),
+                }
+            }
+            ErrorFormat::Json => {
+                let span = self.span.map(|span| {
+                    Json::object([("start", span.start.into()), ("end", span.end.into())])
+                });
+                let trace: Array<Json> = self
+                    .trace
+                    .iter()
+                    .map(|(function, line)| {
+                        Json::object([
+                            ("function", function.as_str().into()),
+                            ("line", (*line).into()),
+                        ])
+                    })
+                    .collect();
+                Json::object([
+                    ("severity", level.into()),
+                    ("code", self.code.clone().into()),
+                    ("message", self.message.as_str().into()),
+                    ("file", file.into()),
+                    ("line", line.into()),
+                    ("column", column.into()),
+                    ("span", span.into()),
+                    ("trace", trace.into()),
+                ])
+                .to_string()
+            }
+            ErrorFormat::Github => {
+                let mut properties = format!("file={}", escape_github(file, true));
+                if let Some(line) = line {
+                    properties.push_str(&format!(",line={line}"));
+                }
+                if let Some(column) = column {
+                    properties.push_str(&format!(",col={column}"));
+                }
+                let mut message = self.message.clone();
+                if let Some(code) = &self.code {
+                    message.push_str(&format!(" [{code}]"));
+                }
+                format!("::{level} {properties}::{}", escape_github(&message, false))
+            }
+        }
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        Self {
+            code: Some(error.code.name().to_owned()),
+            line: Some(error.line),
+            span: Some(error.span),
+            ..Self::new(error.severity(), error.message())
+        }
+    }
+}
+
+impl From<&ScanError> for Diagnostic {
+    fn from(error: &ScanError) -> Self {
+        Self {
+            code: Some(error.code.name().to_owned()),
+            line: Some(error.line),
+            span: Some(error.span),
+            ..Self::new(Severity::Error, error.code.message())
+        }
+    }
+}
+
+impl From<&Warning> for Diagnostic {
+    fn from(warning: &Warning) -> Self {
+        Self {
+            code: Some(warning.lint.name().to_owned()),
+            span: Some(warning.span),
+            ..Self::new(warning.severity(), warning.message.as_str())
+        }
+    }
+}
+
+/// Renders `errors` from `file` in `format`, one per line.
+pub fn format_parse_errors(errors: &Array<ParseError>, format: ErrorFormat, file: &str) -> String {
+    errors.iter().fold(String::new(), |mut output, error| {
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        output.push_str(&Diagnostic::from(error).render(format, file));
+        output
+    })
+}
+
+/// Escapes the characters GitHub workflow commands reserve, in a property
+/// value or in the message.
+fn escape_github(text: &str, property: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '%' => escaped.push_str("%25"),
+            '\r' => escaped.push_str("%0D"),
+            '\n' => escaped.push_str("%0A"),
+            ':' if property => escaped.push_str("%3A"),
+            ',' if property => escaped.push_str("%2C"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
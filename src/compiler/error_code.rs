@@ -0,0 +1,305 @@
+//! Stable codes for every compile and runtime error, so tools need not match
+//! on the wording of messages. `rox explain <code>` prints the explanation.
+//!
+//! Codes are never reused: `E00xx` are compile errors, `E01xx` runtime
+//! errors and `E02xx` errors in bytecode files.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    UnterminatedString,
+    UnexpectedCharacter,
+    ExpectExpression,
+    ExpectRightParen,
+    ExpectEndOfExpression,
+    TooManyConstants,
+    WarningsAsErrors,
+    UnterminatedComment,
+    StackOverflow,
+    StackUnderflow,
+    FuelExhausted,
+    OutOfMemory,
+    Timeout,
+    Interrupted,
+    NotSuspended,
+    InvalidBytecode,
+    UnverifiedBytecode,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 17] = [
+        ErrorCode::UnterminatedString,
+        ErrorCode::UnexpectedCharacter,
+        ErrorCode::ExpectExpression,
+        ErrorCode::ExpectRightParen,
+        ErrorCode::ExpectEndOfExpression,
+        ErrorCode::TooManyConstants,
+        ErrorCode::WarningsAsErrors,
+        ErrorCode::UnterminatedComment,
+        ErrorCode::StackOverflow,
+        ErrorCode::StackUnderflow,
+        ErrorCode::FuelExhausted,
+        ErrorCode::OutOfMemory,
+        ErrorCode::Timeout,
+        ErrorCode::Interrupted,
+        ErrorCode::NotSuspended,
+        ErrorCode::InvalidBytecode,
+        ErrorCode::UnverifiedBytecode,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorCode::UnterminatedString => "E0001",
+            ErrorCode::UnexpectedCharacter => "E0002",
+            ErrorCode::ExpectExpression => "E0003",
+            ErrorCode::ExpectRightParen => "E0004",
+            ErrorCode::ExpectEndOfExpression => "E0005",
+            ErrorCode::TooManyConstants => "E0006",
+            ErrorCode::WarningsAsErrors => "E0007",
+            ErrorCode::UnterminatedComment => "E0008",
+            ErrorCode::StackOverflow => "E0100",
+            ErrorCode::StackUnderflow => "E0101",
+            ErrorCode::FuelExhausted => "E0102",
+            ErrorCode::OutOfMemory => "E0103",
+            ErrorCode::Timeout => "E0104",
+            ErrorCode::Interrupted => "E0105",
+            ErrorCode::NotSuspended => "E0106",
+            ErrorCode::InvalidBytecode => "E0200",
+            ErrorCode::UnverifiedBytecode => "E0201",
+        }
+    }
+
+    /// Looks up a code, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|code| code.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The message of errors with this code. Runtime errors add details,
+    /// such as the limit that was hit.
+    pub fn message(self) -> &'static str {
+        match self {
+            ErrorCode::UnterminatedString => "Unterminated string.",
+            ErrorCode::UnexpectedCharacter => "Unexpected character.",
+            ErrorCode::ExpectExpression => "Expect expression.",
+            ErrorCode::ExpectRightParen => "Expect ')' after expression.",
+            ErrorCode::ExpectEndOfExpression => "Expect end of expression.",
+            ErrorCode::TooManyConstants => "Too many constants in one chunk.",
+            ErrorCode::WarningsAsErrors => "Warnings treated as errors.",
+            ErrorCode::UnterminatedComment => "Unterminated block comment.",
+            ErrorCode::StackOverflow => "Stack overflow.",
+            ErrorCode::StackUnderflow => "Stack underflow.",
+            ErrorCode::FuelExhausted => "Instruction budget exhausted.",
+            ErrorCode::OutOfMemory => "Out of memory.",
+            ErrorCode::Timeout => "Execution timed out.",
+            ErrorCode::Interrupted => "Interrupted.",
+            ErrorCode::NotSuspended => "There is no suspended script to resume.",
+            ErrorCode::InvalidBytecode => "Invalid bytecode file.",
+            ErrorCode::UnverifiedBytecode => "Bytecode failed verification.",
+        }
+    }
+
+    /// What the error means and how to fix it, with an example.
+    pub fn explanation(self) -> &'static str {
+        match self {
+            ErrorCode::UnterminatedString => {
+                "\
+A string literal was opened with `\"` but the file ended before the closing
+quote. Strings may span lines, so the missing quote can be far from where
+the error is reported.
+
+Erroneous code example:
+
+    \"hello
+
+Close the string:
+
+    \"hello\""
+            }
+            ErrorCode::UnexpectedCharacter => {
+                "\
+The scanner found a character that cannot start any token, such as `@`, `#`
+or a non-ASCII letter outside a string or comment.
+
+Erroneous code example:
+
+    1 @ 2
+
+Remove the character, or move it into a string or a comment."
+            }
+            ErrorCode::ExpectExpression => {
+                "\
+The parser needed an expression, such as a number or a parenthesized
+expression, but found another token or the end of the file.
+
+Erroneous code example:
+
+    1 +
+
+Complete the expression:
+
+    1 + 2"
+            }
+            ErrorCode::ExpectRightParen => {
+                "\
+A parenthesized expression was not closed.
+
+Erroneous code example:
+
+    (1 + 2
+
+Add the closing parenthesis:
+
+    (1 + 2)"
+            }
+            ErrorCode::ExpectEndOfExpression => {
+                "\
+A script is a single expression, but more tokens followed it.
+
+Erroneous code example:
+
+    1 + 2 3
+
+Combine the values with an operator, or remove the extra tokens:
+
+    1 + 2 + 3"
+            }
+            ErrorCode::TooManyConstants => {
+                "\
+A chunk can hold at most 256 constants, because instructions refer to them
+with a one-byte index. Every number literal in a script takes a constant.
+
+Split the work across several scripts, or fold literals together:
+
+    1 + 2 + 3    // three constants
+    6            // one constant"
+            }
+            ErrorCode::WarningsAsErrors => {
+                "\
+`--warnings-as-errors` was given and a lint warned. The warnings are
+reported before this error, each with the name of its lint.
+
+Fix the code each warning points at, or silence a lint with `--allow`:
+
+    rox check --lint --warnings-as-errors --allow=division-by-zero script.lox"
+            }
+            ErrorCode::UnterminatedComment => {
+                "\
+A `/*` comment was not closed before the end of the file. Block comments
+nest, so every `/*` inside a comment needs its own `*/`. The error points
+at the `/*` that was left open.
+
+Erroneous code example:
+
+    /* outer /* inner */
+    1 + 2
+
+Close both comments:
+
+    /* outer /* inner */ */
+    1 + 2"
+            }
+            ErrorCode::StackOverflow => {
+                "\
+The script needed more values on the stack than the VM allows. Deeply nested
+expressions each keep their operands on the stack.
+
+Erroneous code example, run with `--max-stack=2`:
+
+    1 + (2 + 3)
+
+Raise the limit with `--max-stack=<n>`, or simplify the expression."
+            }
+            ErrorCode::StackUnderflow => {
+                "\
+An instruction popped a value from an empty stack. Every chunk is verified
+before it runs, which rules this out, so seeing it means a bug in rox.
+
+Please report it with the script that caused it."
+            }
+            ErrorCode::FuelExhausted => {
+                "\
+The script ran more instructions than `--fuel=<n>` allows.
+
+Erroneous code example, run with `--fuel=2`:
+
+    1 + 2 + 3
+
+Raise the budget, or leave out `--fuel` to run without one."
+            }
+            ErrorCode::OutOfMemory => {
+                "\
+The script allocated more memory than `--max-memory=<bytes>` allows.
+
+Raise the limit, or leave out `--max-memory` to run without one."
+            }
+            ErrorCode::Timeout => {
+                "\
+The script ran for longer than `--timeout=<ms>` allows.
+
+Raise the timeout, or leave out `--timeout` to run without one."
+            }
+            ErrorCode::Interrupted => {
+                "\
+The script was stopped from outside while it ran, for example with Ctrl-C
+in the REPL. No value was printed."
+            }
+            ErrorCode::NotSuspended => {
+                "\
+A host asked the VM to resume a script, but no script was suspended. Only
+a script stopped by its step budget or at a breakpoint can be resumed, and
+running another script abandons it."
+            }
+            ErrorCode::InvalidBytecode => {
+                "\
+A `.roxc` file could not be read: it is not a rox bytecode file, was
+written by an incompatible version, or is truncated or corrupt.
+
+Compile the script again with this version of rox:
+
+    rox compile script.lox -o script.roxc"
+            }
+            ErrorCode::UnverifiedBytecode => {
+                "\
+A bytecode file was read, but its code would misbehave when run: it refers
+to missing constants, pops from an empty stack, or declares the wrong stack
+depth. rox checks every file before running it.
+
+Compile the script again from source:
+
+    rox compile script.lox -o script.roxc"
+            }
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_are_unique_and_round_trip() {
+        for (i, code) in ErrorCode::ALL.into_iter().enumerate() {
+            assert_eq!(ErrorCode::from_name(code.name()), Some(code));
+            assert!(
+                ErrorCode::ALL[..i]
+                    .iter()
+                    .all(|other| other.name() != code.name())
+            );
+            assert!(!code.explanation().is_empty());
+        }
+        assert_eq!(
+            ErrorCode::from_name("e0001"),
+            Some(ErrorCode::UnterminatedString)
+        );
+        assert_eq!(ErrorCode::from_name("E9999"), None);
+    }
+}
@@ -0,0 +1,177 @@
+//! Static checks that warn about suspicious code without running it.
+//!
+//! The lints walk the syntax tree from [`super::parse_with`], so they only
+//! see code the compiler accepts. Lints about variables, scopes and
+//! `return` wait for the grammar to have them.
+
+use std::fmt;
+
+use crate::array::Array;
+
+use super::ast::{BinaryOp, Expr, ExprKind, Program, Stmt, UnaryOp};
+use super::{Severity, Span};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lint {
+    DivisionByZero,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 1] = [Lint::DivisionByZero];
+
+    /// The name used to allow the lint, as in `--allow=division-by-zero`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::DivisionByZero => "division-by-zero",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub lint: Lint,
+    pub span: Span,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[line {}] Warning: {} [{}]",
+            self.span.line, self.message, self.lint
+        )
+    }
+}
+
+/// Checks `program` with every lint except the `allowed` ones. The warnings
+/// are in source order.
+pub fn lint(program: &Program, allowed: &[Lint]) -> Array<Warning> {
+    let mut linter = Linter {
+        warnings: Array::default(),
+    };
+    for statement in program.statements.iter() {
+        match statement {
+            Stmt::Expression(expression) => linter.expression(expression),
+        }
+    }
+
+    let mut warnings: Array<Warning> = linter
+        .warnings
+        .iter()
+        .filter(|warning| !allowed.contains(&warning.lint))
+        .cloned()
+        .collect();
+    warnings.sort_by_key(|warning| warning.span.start);
+    warnings
+}
+
+struct Linter {
+    warnings: Array<Warning>,
+}
+
+impl Linter {
+    fn warn(&mut self, lint: Lint, span: Span, message: String) {
+        self.warnings.push(Warning {
+            lint,
+            span,
+            message,
+        });
+    }
+
+    fn expression(&mut self, expression: &Expr) {
+        match &expression.kind {
+            ExprKind::Number(_) => {}
+            ExprKind::Grouping(inner) => self.expression(inner),
+            ExprKind::Unary { operand, .. } => self.expression(operand),
+            ExprKind::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                if *operator == BinaryOp::Divide && is_literal_zero(right) {
+                    self.warn(
+                        Lint::DivisionByZero,
+                        right.span,
+                        "Division by zero.".to_owned(),
+                    );
+                }
+                self.expression(left);
+                self.expression(right);
+            }
+        }
+    }
+}
+
+/// Whether `expression` is a zero literal, perhaps negated or in parentheses.
+fn is_literal_zero(expression: &Expr) -> bool {
+    match &expression.kind {
+        ExprKind::Number(value) => *value == 0.0,
+        ExprKind::Grouping(inner) => is_literal_zero(inner),
+        ExprKind::Unary {
+            operator: UnaryOp::Negate,
+            operand,
+        } => is_literal_zero(operand),
+        ExprKind::Binary { .. } => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler;
+
+    fn lints(source: &str) -> Array<(Lint, usize)> {
+        lint(&compiler::parse(source).unwrap(), &[])
+            .iter()
+            .map(|warning| (warning.lint, warning.span.line))
+            .collect()
+    }
+
+    #[test]
+    fn division_by_zero() {
+        let expected: Array<_> = [(Lint::DivisionByZero, 1)].into_iter().collect();
+        assert_eq!(lints("1 / 0.0"), expected);
+        assert_eq!(lints("1 / -(0)"), expected);
+        assert!(lints("1 / 0.5").is_empty());
+        assert!(lints("1 / (1 - 1)").is_empty());
+
+        let program = compiler::parse("1 +\n2 / 0").unwrap();
+        let warnings = lint(&program, &[]);
+        assert_eq!(
+            warnings[0].to_string(),
+            "[line 2] Warning: Division by zero. [division-by-zero]"
+        );
+        assert_eq!((warnings[0].span.start, warnings[0].span.end), (8, 9));
+    }
+
+    #[test]
+    fn lints_can_be_allowed() {
+        let program = compiler::parse("1 / 0").unwrap();
+        assert!(lint(&program, &[Lint::DivisionByZero]).is_empty());
+        assert_eq!(
+            Lint::from_name("division-by-zero"),
+            Some(Lint::DivisionByZero)
+        );
+        assert_eq!(Lint::from_name("everything"), None);
+        for lint in Lint::ALL {
+            assert_eq!(Lint::from_name(lint.name()), Some(lint));
+        }
+    }
+}
@@ -1,24 +1,45 @@
 use std::fmt;
+use std::num::NonZeroUsize;
 
 use crate::array::Array;
 use crate::vm::chunk::Chunk;
 use crate::vm::opcode::OpCode;
 use crate::vm::value::Value;
 
+use super::ast::{BinaryOp, Expr, ExprKind, Program, Stmt, UnaryOp};
 use super::codegen;
 use super::scanner::{ScanError, Scanner, Token, TokenType};
+use super::{CompileOptions, ErrorCode, ErrorFormat, Span};
 
-const NUM_TOKEN_TYPES: usize = 39;
+const NUM_TOKEN_TYPES: usize = 40;
 
 #[derive(Debug, Clone)]
 pub struct ParseError {
     pub line: usize,
-    pub message: &'static str,
+    pub code: ErrorCode,
+    /// The token the error was found at.
+    pub span: Span,
+}
+
+impl ParseError {
+    pub fn message(&self) -> &'static str {
+        self.code.message()
+    }
+
+    pub fn severity(&self) -> super::Severity {
+        super::Severity::Error
+    }
 }
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "[line {}] Error: {}", self.line, self.message)
+        write!(
+            f,
+            "[line {}] Error: {} [{}]",
+            self.line,
+            self.message(),
+            self.code
+        )
     }
 }
 
@@ -300,10 +321,17 @@ const fn rules() -> [ParseRule; NUM_TOKEN_TYPES] {
             infix: None,
             precedence: Precedence::None,
         },
+        // Comment
+        ParseRule {
+            prefix: None,
+            infix: None,
+            precedence: Precedence::None,
+        },
     ]
 }
 
 pub struct Parser<'src> {
+    source: &'src str,
     scanner: Scanner<'src>,
     current: Token<'src>,
     previous: Token<'src>,
@@ -311,17 +339,23 @@ pub struct Parser<'src> {
     panic_mode: bool,
     errors: Array<ParseError>,
     chunk: Chunk,
+    /// In parse-only mode, the finished subexpressions instead of code.
+    nodes: Option<Array<Expr>>,
+    max_errors: Option<NonZeroUsize>,
+    /// Errors are also printed as they are found, in the human format only.
+    error_format: ErrorFormat,
     rules: [ParseRule; NUM_TOKEN_TYPES],
 }
 
 impl<'src> Parser<'src> {
-    pub fn new(source: &'src str) -> Self {
+    pub fn new(source: &'src str, options: &CompileOptions) -> Self {
         let eof = Token {
             token_type: TokenType::Eof,
-            start: "",
+            start: &source[source.len()..],
             line: 1,
         };
         Self {
+            source,
             scanner: Scanner::new(source),
             current: eof,
             previous: eof,
@@ -329,14 +363,15 @@ impl<'src> Parser<'src> {
             panic_mode: false,
             errors: Array::default(),
             chunk: Chunk::default(),
+            nodes: None,
+            max_errors: options.max_errors,
+            error_format: options.error_format,
             rules: rules(),
         }
     }
 
     pub fn compile(mut self) -> Result<Chunk, Array<ParseError>> {
-        self.advance();
-        self.expression();
-        self.consume(TokenType::Eof, "Expect end of expression.");
+        self.program();
         self.end_compiler();
 
         if self.had_error {
@@ -346,6 +381,78 @@ impl<'src> Parser<'src> {
         }
     }
 
+    /// Parses without generating code, returning the syntax tree.
+    pub fn parse(mut self) -> Result<Program, Array<ParseError>> {
+        self.nodes = Some(Array::default());
+        self.program();
+
+        match self.pop_node() {
+            Some(expression) if !self.had_error => Ok(Program {
+                statements: [Stmt::Expression(*expression)].into_iter().collect(),
+            }),
+            _ => Err(self.errors),
+        }
+    }
+
+    fn program(&mut self) {
+        self.advance();
+        self.expression();
+        self.consume(TokenType::Eof, ErrorCode::ExpectEndOfExpression);
+
+        // After an error, parse the rest statement by statement so that
+        // every independent error is reported.
+        while self.panic_mode && !self.at_error_limit() {
+            self.synchronize();
+            if self.current.token_type == TokenType::Eof {
+                break;
+            }
+            if starts_statement(self.current.token_type) {
+                self.advance();
+            }
+            self.expression();
+            self.consume(TokenType::Eof, ErrorCode::ExpectEndOfExpression);
+        }
+    }
+
+    /// Leaves panic mode by skipping to the next statement boundary.
+    fn synchronize(&mut self) {
+        self.panic_mode = false;
+        while self.current.token_type != TokenType::Eof {
+            if self.previous.token_type == TokenType::Semicolon
+                || starts_statement(self.current.token_type)
+            {
+                return;
+            }
+            self.advance();
+        }
+    }
+
+    fn building_ast(&self) -> bool {
+        self.nodes.is_some()
+    }
+
+    fn span_of(&self, token: &Token<'_>) -> Span {
+        Span::of_token(self.source, token)
+    }
+
+    /// Adds a node that starts at `start` and ends with the previous token.
+    fn push_node(&mut self, kind: ExprKind, start: Span) {
+        let end = self.span_of(&self.previous).end;
+        let node = Expr {
+            kind,
+            span: Span { end, ..start },
+            end_line: self.previous.line,
+        };
+        if let Some(nodes) = self.nodes.as_mut() {
+            nodes.push(node);
+        }
+    }
+
+    /// The last finished subexpression; `None` if an error left it out.
+    fn pop_node(&mut self) -> Option<Box<Expr>> {
+        self.nodes.as_mut()?.pop().map(Box::new)
+    }
+
     fn advance(&mut self) {
         self.previous = self.current;
         loop {
@@ -360,7 +467,7 @@ impl<'src> Parser<'src> {
                 None => {
                     self.current = Token {
                         token_type: TokenType::Eof,
-                        start: "",
+                        start: &self.source[self.source.len()..],
                         line: self.previous.line,
                     };
                     break;
@@ -369,60 +476,78 @@ impl<'src> Parser<'src> {
         }
     }
 
-    fn consume(&mut self, tt: TokenType, message: &'static str) {
+    fn consume(&mut self, tt: TokenType, code: ErrorCode) {
         if self.current.token_type == tt {
             self.advance()
         } else {
-            self.error_at_current(message)
+            self.error_at_current(code)
         }
     }
 
-    fn error_at(&mut self, token: &Token<'_>, message: &'static str) {
-        if self.panic_mode {
+    fn at_error_limit(&self) -> bool {
+        self.max_errors
+            .is_some_and(|max| self.errors.length() >= max.get())
+    }
+
+    fn error_at(&mut self, token: &Token<'_>, code: ErrorCode) {
+        // The limit only stops errors being reported, never the failure.
+        self.had_error = true;
+        if self.panic_mode || self.at_error_limit() {
             return;
         }
         self.panic_mode = true;
 
         match token.token_type {
-            TokenType::Eof => eprintln!("[line {}] Error at end: {message}", token.line),
+            _ if self.error_format != ErrorFormat::Human => {}
+            TokenType::Eof => eprintln!(
+                "[line {}] Error at end: {} [{code}]",
+                token.line,
+                code.message()
+            ),
             _ => eprintln!(
-                "[line {}] Error at '{}': {message}",
-                token.line, token.start
+                "[line {}] Error at '{}': {} [{code}]",
+                token.line,
+                token.start,
+                code.message()
             ),
         }
+        let span = self.span_of(token);
         self.errors.push(ParseError {
             line: token.line,
-            message,
+            code,
+            span,
         });
-        self.had_error = true;
     }
 
-    fn error(&mut self, message: &'static str) {
+    fn error(&mut self, code: ErrorCode) {
         let token = self.previous;
-        self.error_at(&token, message);
+        self.error_at(&token, code);
     }
 
-    fn error_at_current(&mut self, message: &'static str) {
+    fn error_at_current(&mut self, code: ErrorCode) {
         let token = self.current;
-        self.error_at(&token, message);
+        self.error_at(&token, code);
     }
 
     fn report_scan_error(&mut self, err: ScanError) {
-        if self.panic_mode {
+        self.had_error = true;
+        if self.panic_mode || self.at_error_limit() {
             return;
         }
         self.panic_mode = true;
-        eprintln!("[line {}] Error: {}", err.line, err.message);
+        if self.error_format == ErrorFormat::Human {
+            eprintln!("{err}");
+        }
         self.errors.push(ParseError {
             line: err.line,
-            message: err.message,
+            code: err.code,
+            span: err.span,
         });
-        self.had_error = true;
     }
 
-    fn emit_byte(&mut self, byte: u8) {
+    fn emit_opcode(&mut self, opcode: OpCode) {
         let line = self.previous.line;
-        codegen::emit_byte(&mut self.chunk, byte, line);
+        codegen::emit_opcode(&mut self.chunk, opcode, line);
     }
 
     fn emit_return(&mut self) {
@@ -434,10 +559,11 @@ impl<'src> Parser<'src> {
         let line = self.previous.line;
         match codegen::make_constant(&mut self.chunk, value) {
             Ok(idx) => {
-                codegen::emit_bytes(&mut self.chunk, OpCode::Constant as u8, idx, line);
+                codegen::emit_opcode(&mut self.chunk, OpCode::Constant, line);
+                codegen::emit_byte(&mut self.chunk, idx, line);
             }
             Err(_) => {
-                self.error("Too many constants in one chunk.");
+                self.error(ErrorCode::TooManyConstants);
             }
         }
     }
@@ -453,7 +579,7 @@ impl<'src> Parser<'src> {
     fn parse_precedence(&mut self, precedence: Precedence) {
         self.advance();
         let Some(prefix_rule) = self.rules[self.previous.token_type as usize].prefix else {
-            self.error("Expect expression.");
+            self.error(ErrorCode::ExpectExpression);
             return;
         };
         prefix_rule(self);
@@ -468,23 +594,55 @@ impl<'src> Parser<'src> {
     }
 }
 
+fn starts_statement(token_type: TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::Class
+            | TokenType::Fun
+            | TokenType::Var
+            | TokenType::For
+            | TokenType::If
+            | TokenType::While
+            | TokenType::Print
+            | TokenType::Return
+    )
+}
+
 fn number<'src>(parser: &mut Parser<'src>) {
     let value: f64 = parser.previous.start.parse().unwrap_or_default();
+    if parser.building_ast() {
+        let span = parser.span_of(&parser.previous);
+        parser.push_node(ExprKind::Number(value), span);
+        return;
+    }
     parser.emit_constant(value.into());
 }
 
 fn grouping<'src>(parser: &mut Parser<'src>) {
+    let open = parser.span_of(&parser.previous);
     parser.expression();
-    parser.consume(TokenType::RightParen, "Expect ')' after expression.");
+    parser.consume(TokenType::RightParen, ErrorCode::ExpectRightParen);
+
+    if let Some(inner) = parser.pop_node() {
+        parser.push_node(ExprKind::Grouping(inner), open);
+    }
 }
 
 fn unary<'src>(parser: &mut Parser<'src>) {
     let operator_type = parser.previous.token_type;
+    let start = parser.span_of(&parser.previous);
 
     parser.parse_precedence(Precedence::Unary);
 
+    if parser.building_ast() {
+        if let Some(operand) = parser.pop_node() {
+            let operator = UnaryOp::Negate;
+            parser.push_node(ExprKind::Unary { operator, operand }, start);
+        }
+        return;
+    }
     if operator_type == TokenType::Minus {
-        parser.emit_byte(OpCode::Negate as u8);
+        parser.emit_opcode(OpCode::Negate);
     }
 }
 
@@ -493,11 +651,32 @@ fn binary<'src>(parser: &mut Parser<'src>) {
     let rule = parser.rules[operator_type as usize];
     parser.parse_precedence(Precedence::next(rule.precedence));
 
+    if parser.building_ast() {
+        let operator = match operator_type {
+            TokenType::Plus => BinaryOp::Add,
+            TokenType::Minus => BinaryOp::Subtract,
+            TokenType::Star => BinaryOp::Multiply,
+            TokenType::Slash => BinaryOp::Divide,
+            _ => unreachable!("no binary rule for {operator_type:?}"),
+        };
+        if let (Some(right), Some(left)) = (parser.pop_node(), parser.pop_node()) {
+            let start = left.span;
+            parser.push_node(
+                ExprKind::Binary {
+                    operator,
+                    left,
+                    right,
+                },
+                start,
+            );
+        }
+        return;
+    }
     match operator_type {
-        TokenType::Plus => parser.emit_byte(OpCode::Add as u8),
-        TokenType::Minus => parser.emit_byte(OpCode::Subtract as u8),
-        TokenType::Star => parser.emit_byte(OpCode::Multiply as u8),
-        TokenType::Slash => parser.emit_byte(OpCode::Divide as u8),
+        TokenType::Plus => parser.emit_opcode(OpCode::Add),
+        TokenType::Minus => parser.emit_opcode(OpCode::Subtract),
+        TokenType::Star => parser.emit_opcode(OpCode::Multiply),
+        TokenType::Slash => parser.emit_opcode(OpCode::Divide),
         _ => {}
     }
 }
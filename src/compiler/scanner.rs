@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use super::{ErrorCode, Span};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum TokenType {
@@ -42,6 +44,9 @@ pub enum TokenType {
     Var,
     While,
     Eof,
+    /// A `//` line comment or a `/* */` block comment, only produced by
+    /// [`Scanner::with_comments`].
+    Comment,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -51,11 +56,18 @@ pub struct Token<'a> {
     pub line: usize,
 }
 
+/// The byte offset of `token` in `source`, which it was scanned from.
+pub fn offset_of(source: &str, token: &Token<'_>) -> usize {
+    token.start.as_ptr() as usize - source.as_ptr() as usize
+}
+
 #[derive(Debug, Error)]
-#[error("[line {line}] Error: {message}")]
+#[error("[line {line}] Error: {} [{code}]", code.message())]
 pub struct ScanError {
-    pub message: &'static str,
+    pub code: ErrorCode,
     pub line: usize,
+    /// The text that could not be scanned.
+    pub span: Span,
 }
 
 pub struct Scanner<'a> {
@@ -63,6 +75,7 @@ pub struct Scanner<'a> {
     start: usize,
     current: usize,
     line: usize,
+    comments: bool,
 }
 
 impl<'a> Scanner<'a> {
@@ -72,6 +85,15 @@ impl<'a> Scanner<'a> {
             start: 0,
             current: 0,
             line: 1,
+            comments: false,
+        }
+    }
+
+    /// A scanner that returns comments as tokens instead of skipping them.
+    pub fn with_comments(source: &'a str) -> Self {
+        Self {
+            comments: true,
+            ..Self::new(source)
         }
     }
 
@@ -120,14 +142,17 @@ impl<'a> Scanner<'a> {
         }
     }
 
-    fn error(&self, message: &'static str) -> ScanError {
+    fn error(&self, code: ErrorCode) -> ScanError {
+        let text = &self.source[self.start..self.current];
+        let start_line = self.line - text.matches('\n').count();
         ScanError {
-            message,
+            code,
             line: self.line,
+            span: Span::new(self.source, self.start, self.current, start_line),
         }
     }
 
-    fn skip_whitespace(&mut self) {
+    fn skip_whitespace(&mut self) -> Result<(), ScanError> {
         loop {
             let c = self.peek();
             match c {
@@ -138,18 +163,59 @@ impl<'a> Scanner<'a> {
                     self.line += 1;
                     self.advance();
                 }
-                b'/' => {
-                    if self.peek_next() == b'/' {
+                b'/' if self.comments => break,
+                b'/' => match self.peek_next() {
+                    b'/' => {
                         while self.peek() != b'\n' && !self.is_at_end() {
                             self.advance();
                         }
-                    } else {
-                        break;
                     }
-                }
+                    b'*' => {
+                        self.start = self.current;
+                        self.current += 2;
+                        self.block_comment()?;
+                    }
+                    _ => break,
+                },
                 _ => break,
             }
         }
+        Ok(())
+    }
+
+    /// Skips the rest of a `/* */` comment whose opening `/*` starts at
+    /// `self.start`. Comments nest, so each `/*` needs its own `*/`.
+    fn block_comment(&mut self) -> Result<(), ScanError> {
+        let open_line = self.line;
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                let open = self.start;
+                return Err(ScanError {
+                    code: ErrorCode::UnterminatedComment,
+                    line: open_line,
+                    span: Span::new(self.source, open, open + 2, open_line),
+                });
+            }
+            match (self.peek(), self.peek_next()) {
+                (b'/', b'*') => {
+                    self.current += 2;
+                    depth += 1;
+                }
+                (b'*', b'/') => {
+                    self.current += 2;
+                    depth -= 1;
+                }
+                (b'\n', _) => {
+                    self.line += 1;
+                    self.advance();
+                }
+                _ => {
+                    self.advance();
+                }
+            }
+        }
+        Ok(())
     }
 
     fn string(&mut self) -> Result<Token<'a>, ScanError> {
@@ -161,7 +227,7 @@ impl<'a> Scanner<'a> {
         }
 
         if self.is_at_end() {
-            return Err(self.error("Unterminated string."));
+            return Err(self.error(ErrorCode::UnterminatedString));
         }
 
         self.advance();
@@ -238,7 +304,9 @@ impl<'a> Scanner<'a> {
     }
 
     pub fn scan_token(&mut self) -> Option<Result<Token<'a>, ScanError>> {
-        self.skip_whitespace();
+        if let Err(err) = self.skip_whitespace() {
+            return Some(Err(err));
+        }
         self.start = self.current;
 
         if self.is_at_end() {
@@ -257,6 +325,16 @@ impl<'a> Scanner<'a> {
             b'.' => TokenType::Dot,
             b'-' => TokenType::Minus,
             b'+' => TokenType::Plus,
+            b'/' if self.match_char(b'/') => {
+                while self.peek() != b'\n' && !self.is_at_end() {
+                    self.advance();
+                }
+                TokenType::Comment
+            }
+            b'/' if self.match_char(b'*') => match self.block_comment() {
+                Ok(()) => TokenType::Comment,
+                Err(err) => return Some(Err(err)),
+            },
             b'/' => TokenType::Slash,
             b'*' => TokenType::Star,
             b'!' => {
@@ -290,7 +368,7 @@ impl<'a> Scanner<'a> {
             b'"' => return Some(self.string()),
             _ if is_digit(c) => return Some(Ok(self.number())),
             _ if is_alpha(c) => return Some(Ok(self.identifier())),
-            _ => return Some(Err(self.error("Unexpected character."))),
+            _ => return Some(Err(self.error(ErrorCode::UnexpectedCharacter))),
         };
 
         Some(Ok(self.make_token(token_type)))
@@ -332,9 +410,70 @@ mod tests {
         assert_eq!(token.line, 2);
     }
 
+    #[test]
+    fn comments_can_be_kept() {
+        let mut scanner = Scanner::with_comments(
+            "1 / 2 // half
+// done",
+        );
+        let tokens: Array<_> = std::iter::from_fn(|| scanner.scan_token())
+            .map(|token| token.unwrap())
+            .collect();
+        let comments: Array<_> = tokens
+            .iter()
+            .filter(|token| token.token_type == TokenType::Comment)
+            .map(|token| (token.start, token.line))
+            .collect();
+        assert_eq!(tokens.length(), 5);
+        assert_eq!(tokens[1].token_type, TokenType::Slash);
+        assert_eq!(comments[0], ("// half", 1));
+        assert_eq!(comments[1], ("// done", 2));
+    }
+
+    #[test]
+    fn block_comments_nest_and_count_lines() {
+        let mut scanner = Scanner::new("/* a /* b\n */ c\n*/ 1 /**/ 2 */* 3 */ 4");
+        let tokens: Array<_> = std::iter::from_fn(|| scanner.scan_token())
+            .map(|token| {
+                let token = token.unwrap();
+                (token.token_type, token.start, token.line)
+            })
+            .collect();
+        let expected: Array<_> = [
+            (TokenType::Number, "1", 3),
+            (TokenType::Number, "2", 3),
+            (TokenType::Star, "*", 3),
+            (TokenType::Number, "4", 3),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(tokens, expected);
+
+        let mut scanner = Scanner::with_comments("1 /* a /* b */\n */ 2");
+        scanner.scan_token();
+        let comment = scanner.scan_token().unwrap().unwrap();
+        assert_eq!(comment.token_type, TokenType::Comment);
+        assert_eq!(comment.start, "/* a /* b */\n */");
+        assert_eq!(comment.line, 2);
+    }
+
+    #[test]
+    fn unterminated_block_comment_points_at_its_start() {
+        let source = "1 +\n  /* open /* nested */\n2";
+        for mut scanner in [Scanner::new(source), Scanner::with_comments(source)] {
+            let err = std::iter::from_fn(|| scanner.scan_token())
+                .find_map(Result::err)
+                .unwrap();
+            assert_eq!(err.code, ErrorCode::UnterminatedComment);
+            assert_eq!(err.line, 2);
+            assert_eq!((err.span.start, err.span.end, err.span.column), (6, 8, 3));
+            assert!(scanner.scan_token().is_none());
+        }
+    }
+
     #[test]
     fn single_char_tokens() {
-        let mut scanner = Scanner::new("(){},.-+/*;");
+        let mut scanner = Scanner::new("(){},.-+/ *;");
         let expected = vec![
             TokenType::LeftParen,
             TokenType::RightParen,
@@ -424,7 +563,7 @@ mod tests {
         let result = scanner.scan_token().unwrap();
         assert!(result.is_err());
         let err = result.unwrap_err();
-        assert_eq!(err.message, "Unterminated string.");
+        assert_eq!(err.code, ErrorCode::UnterminatedString);
     }
 
     #[test]
@@ -529,7 +668,7 @@ mod tests {
         let mut scanner = Scanner::new("@");
         if let Some(Err(e)) = scanner.scan_token() {
             assert_eq!(e.line, 1);
-            assert_eq!(e.message, "Unexpected character.");
+            assert_eq!(e.code, ErrorCode::UnexpectedCharacter);
         } else {
             panic!("Expected error");
         }
@@ -538,10 +677,14 @@ mod tests {
     #[test]
     fn error_display_format() {
         let err = ScanError {
-            message: "Unexpected character.",
+            code: ErrorCode::UnexpectedCharacter,
             line: 42,
+            span: Span::new("@", 0, 1, 42),
         };
-        assert_eq!(err.to_string(), "[line 42] Error: Unexpected character.");
+        assert_eq!(
+            err.to_string(),
+            "[line 42] Error: Unexpected character. [E0002]"
+        );
     }
 
     #[test]
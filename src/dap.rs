@@ -0,0 +1,582 @@
+//! `rox dap`: a Debug Adapter Protocol server over stdin and stdout.
+//!
+//! Scripts run on the VM's [`Debugger`]. Their output is captured and sent
+//! to the client as `output` events, as stdout carries the protocol.
+
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use crate::array::Array;
+use crate::compiler;
+use crate::json::Json;
+use crate::transport;
+use crate::vm::debugger::{Debugger, Pause, PauseReason, StepMode};
+use crate::vm::error::RoxError;
+use crate::vm::value::Value;
+use crate::vm::{RunState, Vm};
+
+/// Scripts run on a single thread.
+const THREAD_ID: usize = 1;
+/// There is one frame until the language has calls.
+const FRAME_ID: usize = 1;
+
+const LOCALS_REFERENCE: usize = 1;
+const GLOBALS_REFERENCE: usize = 2;
+const STACK_REFERENCE: usize = 3;
+
+/// Serves requests from `input` until the client disconnects or the input ends.
+pub fn run(mut input: impl BufRead, output: impl Write) -> io::Result<()> {
+    let mut server = Server::new(output);
+    while let Some(message) = transport::read_message(&mut input)? {
+        if !server.handle(&message)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// The script named by the `launch` request.
+struct Program {
+    path: String,
+    source: String,
+    /// Lines that have code, where breakpoints can be hit.
+    code_lines: Array<usize>,
+}
+
+struct Server<W: Write> {
+    output: W,
+    seq: usize,
+    vm: Vm<Array<u8>>,
+    program: Option<Program>,
+    stop_on_entry: bool,
+    configured: bool,
+    started: bool,
+    /// Whether a paused script is waiting to be resumed.
+    paused: bool,
+}
+
+impl<W: Write> Server<W> {
+    fn new(output: W) -> Self {
+        let mut vm = Vm::with_output(Array::default());
+        vm.set_debugger(Some(Debugger::default()));
+        Self {
+            output,
+            seq: 0,
+            vm,
+            program: None,
+            stop_on_entry: false,
+            configured: false,
+            started: false,
+            paused: false,
+        }
+    }
+
+    /// Handles one message. Returns `false` once the session is over.
+    fn handle(&mut self, message: &Json) -> io::Result<bool> {
+        if message.get("type").and_then(Json::as_str) != Some("request") {
+            return Ok(true);
+        }
+        let command = message.get("command").and_then(Json::as_str).unwrap_or("");
+        let arguments = message.get("arguments").unwrap_or(&Json::Null);
+
+        match command {
+            "initialize" => {
+                let capabilities =
+                    Json::object([("supportsConfigurationDoneRequest", true.into())]);
+                self.respond(message, capabilities)?;
+                self.event("initialized", Json::Null)?;
+            }
+            "launch" => match self.launch(arguments) {
+                Ok(()) => {
+                    self.respond(message, Json::Null)?;
+                    self.start_if_ready()?;
+                }
+                Err(e) => self.fail(message, &e)?,
+            },
+            "setBreakpoints" => {
+                let body = self.set_breakpoints(arguments);
+                self.respond(message, body)?;
+            }
+            "configurationDone" => {
+                self.configured = true;
+                self.respond(message, Json::Null)?;
+                self.start_if_ready()?;
+            }
+            "threads" => {
+                let thread = Json::object([("id", THREAD_ID.into()), ("name", "main".into())]);
+                let threads = Json::Array([thread].into_iter().collect());
+                self.respond(message, Json::object([("threads", threads)]))?;
+            }
+            "continue" | "next" | "stepIn" | "stepOut" if !self.paused => {
+                self.fail(message, "The script is not paused.")?;
+            }
+            "continue" => {
+                self.respond(
+                    message,
+                    Json::object([("allThreadsContinued", true.into())]),
+                )?;
+                self.resume(StepMode::Continue)?;
+            }
+            "next" => {
+                self.respond(message, Json::Null)?;
+                self.resume(StepMode::Next)?;
+            }
+            "stepIn" => {
+                self.respond(message, Json::Null)?;
+                self.resume(StepMode::Step)?;
+            }
+            "stepOut" => {
+                self.respond(message, Json::Null)?;
+                self.resume(StepMode::Finish)?;
+            }
+            "stackTrace" => {
+                let body = self.stack_trace();
+                self.respond(message, body)?;
+            }
+            "scopes" => {
+                let scope = |name: &str, reference: usize| {
+                    Json::object([
+                        ("name", name.into()),
+                        ("variablesReference", reference.into()),
+                        ("expensive", false.into()),
+                    ])
+                };
+                let scopes = [
+                    scope("Locals", LOCALS_REFERENCE),
+                    scope("Globals", GLOBALS_REFERENCE),
+                    scope("Stack", STACK_REFERENCE),
+                ];
+                let scopes = Json::Array(scopes.into_iter().collect());
+                self.respond(message, Json::object([("scopes", scopes)]))?;
+            }
+            "variables" => {
+                let reference = arguments.get("variablesReference").and_then(Json::as_usize);
+                let body = self.variables(reference);
+                self.respond(message, body)?;
+            }
+            "disconnect" | "terminate" => {
+                self.respond(message, Json::Null)?;
+                return Ok(false);
+            }
+            _ => self.fail(message, &format!("Unsupported request '{command}'."))?,
+        }
+        Ok(true)
+    }
+
+    fn launch(&mut self, arguments: &Json) -> Result<(), String> {
+        let path = arguments
+            .get("program")
+            .and_then(Json::as_str)
+            .ok_or("Missing 'program' to launch.")?;
+        let source = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        let chunk = compiler::compile(&source).map_err(|e| RoxError::from(e).to_string())?;
+
+        let mut code_lines: Array<usize> = Array::default();
+        for &line in chunk.lines.iter() {
+            if !code_lines.contains(&line) {
+                code_lines.push(line);
+            }
+        }
+        self.stop_on_entry = arguments
+            .get("stopOnEntry")
+            .and_then(Json::as_bool)
+            .unwrap_or(false);
+        self.program = Some(Program {
+            path: path.to_owned(),
+            source,
+            code_lines,
+        });
+        Ok(())
+    }
+
+    fn set_breakpoints(&mut self, arguments: &Json) -> Json {
+        let requested = arguments
+            .get("breakpoints")
+            .and_then(Json::as_array)
+            .unwrap_or_default();
+        let debugger = self
+            .vm
+            .debugger_mut()
+            .expect("the server installs a debugger");
+        // A client sends every breakpoint of a source each time, and there is
+        // only one source.
+        debugger.clear_breakpoints();
+
+        let mut breakpoints = Array::default();
+        for breakpoint in requested {
+            let Some(line) = breakpoint.get("line").and_then(Json::as_usize) else {
+                continue;
+            };
+            debugger.add_breakpoint(line);
+            let verified = self
+                .program
+                .as_ref()
+                .is_none_or(|program| program.code_lines.contains(&line));
+            breakpoints.push(Json::object([
+                ("verified", verified.into()),
+                ("line", line.into()),
+            ]));
+        }
+        Json::object([("breakpoints", breakpoints.into())])
+    }
+
+    /// Runs the script once it is both launched and configured.
+    fn start_if_ready(&mut self) -> io::Result<()> {
+        let Some(program) = &self.program else {
+            return Ok(());
+        };
+        if !self.configured || self.started {
+            return Ok(());
+        }
+        self.started = true;
+
+        let mode = if self.stop_on_entry {
+            StepMode::Step
+        } else {
+            StepMode::Continue
+        };
+        self.vm
+            .debugger_mut()
+            .expect("the server installs a debugger")
+            .set_mode(mode);
+        let result = compiler::compile(&program.source)
+            .map_err(RoxError::from)
+            .and_then(|chunk| self.vm.run(chunk));
+        self.report(result, self.stop_on_entry)
+    }
+
+    fn resume(&mut self, mode: StepMode) -> io::Result<()> {
+        self.vm
+            .debugger_mut()
+            .expect("the server installs a debugger")
+            .set_mode(mode);
+        let result = self.vm.resume();
+        self.report(result, false)
+    }
+
+    /// Tells the client what the script printed and where it stopped.
+    fn report(&mut self, result: Result<RunState, RoxError>, entry: bool) -> io::Result<()> {
+        let printed = std::mem::take(self.vm.output_mut());
+        if !printed.is_empty() {
+            let text = String::from_utf8_lossy(&printed).into_owned();
+            self.output_event("stdout", text)?;
+        }
+
+        self.paused = false;
+        let exit_code = match result {
+            Ok(RunState::Paused(Pause { reason, .. })) => {
+                self.paused = true;
+                let reason = match reason {
+                    _ if entry => "entry",
+                    PauseReason::Breakpoint => "breakpoint",
+                    PauseReason::Step => "step",
+                };
+                return self.stopped(reason);
+            }
+            // The server never sets a step budget.
+            Ok(RunState::Suspended) => {
+                self.paused = true;
+                return self.stopped("pause");
+            }
+            Ok(RunState::Finished) => 0,
+            Err(e) => {
+                self.output_event("stderr", format!("{e}\n"))?;
+                match e {
+                    RoxError::Compile(_) => 65,
+                    _ => 70,
+                }
+            }
+        };
+        self.event("exited", Json::object([("exitCode", exit_code.into())]))?;
+        self.event("terminated", Json::Null)
+    }
+
+    fn stack_trace(&self) -> Json {
+        let mut frames = Array::default();
+        if let (Some(program), Some(line)) = (&self.program, self.vm.current_line()) {
+            let name = Path::new(&program.path)
+                .file_name()
+                .map_or(program.path.clone(), |name| {
+                    name.to_string_lossy().into_owned()
+                });
+            let source = Json::object([
+                ("name", name.into()),
+                ("path", program.path.as_str().into()),
+            ]);
+            frames.push(Json::object([
+                ("id", FRAME_ID.into()),
+                ("name", "script".into()),
+                ("source", source),
+                ("line", line.into()),
+                ("column", 1usize.into()),
+            ]));
+        }
+        let total = frames.length();
+        Json::object([
+            ("stackFrames", frames.into()),
+            ("totalFrames", total.into()),
+        ])
+    }
+
+    fn variables(&self, reference: Option<usize>) -> Json {
+        let variable = |name: String, value: &Value| {
+            Json::object([
+                ("name", name.into()),
+                ("value", value.to_string().into()),
+                ("variablesReference", 0usize.into()),
+            ])
+        };
+        let named = |variables: &[(String, Value)]| -> Array<Json> {
+            variables
+                .iter()
+                .map(|(name, value)| variable(name.clone(), value))
+                .collect()
+        };
+        let variables = match reference {
+            Some(LOCALS_REFERENCE) => named(self.vm.locals()),
+            Some(GLOBALS_REFERENCE) => named(self.vm.globals()),
+            Some(STACK_REFERENCE) => self
+                .vm
+                .stack_values()
+                .iter()
+                .enumerate()
+                .map(|(slot, value)| variable(format!("[{slot}]"), value))
+                .collect(),
+            _ => Array::default(),
+        };
+        Json::object([("variables", variables.into())])
+    }
+
+    fn stopped(&mut self, reason: &str) -> io::Result<()> {
+        let body = Json::object([
+            ("reason", reason.into()),
+            ("threadId", THREAD_ID.into()),
+            ("allThreadsStopped", true.into()),
+        ]);
+        self.event("stopped", body)
+    }
+
+    fn output_event(&mut self, category: &str, output: String) -> io::Result<()> {
+        let body = Json::object([("category", category.into()), ("output", output.into())]);
+        self.event("output", body)
+    }
+
+    fn respond(&mut self, request: &Json, body: Json) -> io::Result<()> {
+        self.response(request, true, None, body)
+    }
+
+    fn fail(&mut self, request: &Json, message: &str) -> io::Result<()> {
+        self.response(request, false, Some(message), Json::Null)
+    }
+
+    fn response(
+        &mut self,
+        request: &Json,
+        success: bool,
+        message: Option<&str>,
+        body: Json,
+    ) -> io::Result<()> {
+        let request_seq = request.get("seq").and_then(Json::as_usize).unwrap_or(0);
+        let command = request.get("command").and_then(Json::as_str).unwrap_or("");
+        let mut response = Json::object([
+            ("seq", self.next_seq().into()),
+            ("type", "response".into()),
+            ("request_seq", request_seq.into()),
+            ("success", success.into()),
+            ("command", command.into()),
+            ("body", body),
+        ]);
+        if let Some(message) = message {
+            response = response.with("message", message);
+        }
+        transport::write_message(&mut self.output, &response)
+    }
+
+    fn event(&mut self, event: &str, body: Json) -> io::Result<()> {
+        let event = Json::object([
+            ("seq", self.next_seq().into()),
+            ("type", "event".into()),
+            ("event", event.into()),
+            ("body", body),
+        ]);
+        transport::write_message(&mut self.output, &event)
+    }
+
+    fn next_seq(&mut self) -> usize {
+        self.seq += 1;
+        self.seq
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frames `requests` as a client would, numbering them from 1.
+    fn client_input<const N: usize>(requests: [(&str, Json); N]) -> Array<u8> {
+        let mut input = Array::default();
+        for (seq, (command, arguments)) in requests.into_iter().enumerate() {
+            let request = Json::object([
+                ("seq", (seq + 1).into()),
+                ("type", "request".into()),
+                ("command", command.into()),
+                ("arguments", arguments),
+            ]);
+            transport::write_message(&mut input, &request).unwrap();
+        }
+        input
+    }
+
+    fn server_output(input: &[u8]) -> Array<Json> {
+        let mut output = Array::default();
+        run(input, &mut output).unwrap();
+        let mut reader: &[u8] = &output;
+        let mut messages = Array::default();
+        while let Some(message) = transport::read_message(&mut reader).unwrap() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    /// A short description of each message: the command of a response or
+    /// the name of an event.
+    fn summary(message: &Json) -> String {
+        let field = |name| message.get(name).and_then(Json::as_str).unwrap_or("");
+        match field("type") {
+            "response" if message.get("success").and_then(Json::as_bool) == Some(false) => {
+                format!("!{}", field("command"))
+            }
+            "response" => field("command").to_owned(),
+            _ => format!("event {}", field("event")),
+        }
+    }
+
+    fn script(name: &str, source: &str) -> String {
+        let path = std::env::temp_dir().join(format!("rox-dap-{}-{name}.lox", std::process::id()));
+        std::fs::write(&path, source).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn debugs_a_script_with_breakpoints() {
+        let path = script("breakpoints", "1 +\n2 *\n3\n");
+        let line = |line: usize| Json::object([("line", line.into())]);
+        let input = client_input([
+            ("initialize", Json::object([("adapterID", "rox".into())])),
+            ("launch", Json::object([("program", path.as_str().into())])),
+            (
+                "setBreakpoints",
+                Json::object([
+                    ("source", Json::object([("path", path.as_str().into())])),
+                    (
+                        "breakpoints",
+                        [line(2), line(9)].into_iter().collect::<Array<_>>().into(),
+                    ),
+                ]),
+            ),
+            ("configurationDone", Json::Null),
+            ("threads", Json::Null),
+            ("stackTrace", Json::object([("threadId", THREAD_ID.into())])),
+            ("scopes", Json::object([("frameId", FRAME_ID.into())])),
+            (
+                "variables",
+                Json::object([("variablesReference", STACK_REFERENCE.into())]),
+            ),
+            ("next", Json::object([("threadId", THREAD_ID.into())])),
+            ("continue", Json::object([("threadId", THREAD_ID.into())])),
+            ("next", Json::object([("threadId", THREAD_ID.into())])),
+            ("disconnect", Json::Null),
+        ]);
+        let messages = server_output(&input);
+        std::fs::remove_file(&path).unwrap();
+
+        let summaries: Vec<String> = messages.iter().map(summary).collect();
+        assert_eq!(
+            summaries,
+            [
+                "initialize",
+                "event initialized",
+                "launch",
+                "setBreakpoints",
+                "configurationDone",
+                "event stopped",
+                "threads",
+                "stackTrace",
+                "scopes",
+                "variables",
+                "next",
+                "event stopped",
+                "continue",
+                "event output",
+                "event exited",
+                "event terminated",
+                "!next",
+                "disconnect",
+            ]
+        );
+
+        let body = |index: usize| messages[index].get("body").unwrap();
+        let verified: Vec<bool> = body(3)
+            .get("breakpoints")
+            .and_then(Json::as_array)
+            .unwrap()
+            .iter()
+            .map(|b| b.get("verified").and_then(Json::as_bool).unwrap())
+            .collect();
+        assert_eq!(verified, [true, false]);
+        assert_eq!(
+            body(5).get("reason").and_then(Json::as_str),
+            Some("breakpoint")
+        );
+        let frame = &body(7).get("stackFrames").and_then(Json::as_array).unwrap()[0];
+        assert_eq!(frame.get("line").and_then(Json::as_usize), Some(2));
+        let variable = &body(9).get("variables").and_then(Json::as_array).unwrap()[0];
+        assert_eq!(variable.get("value").and_then(Json::as_str), Some("1"));
+        assert_eq!(body(11).get("reason").and_then(Json::as_str), Some("step"));
+        assert_eq!(body(13).get("output").and_then(Json::as_str), Some("7\n"));
+        assert_eq!(body(14).get("exitCode").and_then(Json::as_usize), Some(0));
+    }
+
+    #[test]
+    fn reports_launch_failures_and_stops_on_entry() {
+        let missing = client_input([(
+            "launch",
+            Json::object([("program", "/nonexistent.lox".into())]),
+        )]);
+        let messages = server_output(&missing);
+        assert_eq!(summary(&messages[0]), "!launch");
+
+        let path = script("entry", "1");
+        let input = client_input([
+            (
+                "launch",
+                Json::object([
+                    ("program", path.as_str().into()),
+                    ("stopOnEntry", true.into()),
+                ]),
+            ),
+            ("configurationDone", Json::Null),
+            ("stepIn", Json::Null),
+            ("evaluate", Json::Null),
+        ]);
+        let messages = server_output(&input);
+        std::fs::remove_file(&path).unwrap();
+
+        let summaries: Vec<String> = messages.iter().map(summary).collect();
+        assert_eq!(
+            summaries,
+            [
+                "launch",
+                "configurationDone",
+                "event stopped",
+                "stepIn",
+                "event output",
+                "event exited",
+                "event terminated",
+                "!evaluate",
+            ]
+        );
+        let reason = messages[2].get("body").and_then(|b| b.get("reason"));
+        assert_eq!(reason.and_then(Json::as_str), Some("entry"));
+    }
+}
@@ -0,0 +1,334 @@
+//! `rox debug`: a gdb-like command line around the VM's debugger.
+
+use std::io::{BufRead, Write};
+
+use crate::compiler;
+use crate::vm::debugger::{Debugger, Pause, PauseReason, StepMode};
+use crate::vm::error::RoxError;
+use crate::vm::value::Value;
+use crate::vm::{RunState, Vm};
+
+const PROMPT: &str = "(rox) ";
+
+const HELP: &str = "\
+break <line>      Pause when execution reaches <line> (b)
+delete <line>     Remove the breakpoint on <line> (d)
+info breakpoints  List breakpoints (info b)
+run               Start the script from the beginning (r)
+continue          Run until the next breakpoint (c)
+step              Run to the next line, entering calls (s)
+next              Run to the next line, stepping over calls (n)
+finish            Run until the current function returns
+stepi [<n>]       Execute <n> instructions, 1 by default (si)
+stack             Show the value stack
+locals            Show local variables
+globals           Show global variables
+backtrace         Show the call frames (bt)
+list              Show the source around the current line (l)
+help              Show this help
+quit              Leave the debugger (q)
+An empty line repeats the previous command.";
+
+/// Runs a debugging session over `source`, reading commands from `commands`
+/// until `quit` or the end of input.
+pub fn run<W: Write>(
+    vm: &mut Vm<W>,
+    source: &str,
+    commands: impl BufRead,
+    out: &mut impl Write,
+) -> Result<(), RoxError> {
+    // Report compile errors before asking for any commands.
+    compiler::compile_with(source, vm.compile_options())?;
+    vm.set_debugger(Some(Debugger::default()));
+
+    let mut session = Session {
+        vm,
+        source,
+        running: false,
+    };
+    let mut lines = commands.lines();
+    let mut previous = String::new();
+    loop {
+        write!(out, "{PROMPT}")?;
+        out.flush()?;
+        let Some(line) = lines.next().transpose()? else {
+            break;
+        };
+        let line = match line.trim() {
+            "" => previous.clone(),
+            line => line.to_owned(),
+        };
+        if !session.command(&line, out)? {
+            break;
+        }
+        previous = line;
+    }
+    session.vm.set_debugger(None);
+    session.vm.reset();
+    Ok(())
+}
+
+struct Session<'a, W: Write> {
+    vm: &'a mut Vm<W>,
+    source: &'a str,
+    running: bool,
+}
+
+impl<W: Write> Session<'_, W> {
+    /// Executes one command. Returns `false` when the session should end.
+    fn command(&mut self, line: &str, out: &mut impl Write) -> Result<bool, RoxError> {
+        let (name, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let argument = argument.trim();
+        match name {
+            "break" | "b" => match argument.parse() {
+                Ok(line) => {
+                    self.debugger().add_breakpoint(line);
+                    writeln!(out, "Breakpoint at line {line}.")?;
+                }
+                Err(_) => writeln!(out, "Expected a line number.")?,
+            },
+            "delete" | "d" => match argument.parse() {
+                Ok(line) if self.debugger().remove_breakpoint(line) => {
+                    writeln!(out, "Deleted breakpoint at line {line}.")?
+                }
+                Ok(line) => writeln!(out, "No breakpoint at line {line}.")?,
+                Err(_) => writeln!(out, "Expected a line number.")?,
+            },
+            "info" if matches!(argument, "breakpoints" | "b") => {
+                let breakpoints = self.debugger().breakpoints();
+                if breakpoints.is_empty() {
+                    writeln!(out, "No breakpoints.")?;
+                }
+                for line in breakpoints.iter() {
+                    writeln!(out, "Breakpoint at line {line}.")?;
+                }
+            }
+            "run" | "r" => self.start(StepMode::Continue, out)?,
+            "continue" | "c" => self.proceed(StepMode::Continue, out)?,
+            "step" | "s" => self.proceed(StepMode::Step, out)?,
+            "next" | "n" => self.proceed(StepMode::Next, out)?,
+            "finish" => self.proceed(StepMode::Finish, out)?,
+            "stepi" | "si" => match argument {
+                "" => self.step_instructions(1, out)?,
+                _ => match argument.parse() {
+                    Ok(count) if count > 0 => self.step_instructions(count, out)?,
+                    _ => writeln!(out, "Expected a number of instructions.")?,
+                },
+            },
+            "stack" => writeln!(out, "{}", self.vm.stack())?,
+            "locals" => write_variables(self.vm.locals(), "(no locals)", out)?,
+            "globals" => write_variables(self.vm.globals(), "(no globals)", out)?,
+            "backtrace" | "bt" => match self.vm.current_line() {
+                Some(line) => writeln!(out, "#0  script at line {line}")?,
+                None => writeln!(out, "No stack.")?,
+            },
+            "list" | "l" => self.list(out)?,
+            "help" => writeln!(out, "{HELP}")?,
+            "quit" | "q" => return Ok(false),
+            _ => writeln!(out, "Unknown command '{name}'. Try help.")?,
+        }
+        Ok(true)
+    }
+
+    fn debugger(&mut self) -> &mut Debugger {
+        self.vm
+            .debugger_mut()
+            .expect("the session installs a debugger")
+    }
+
+    /// Runs the script from the beginning.
+    fn start(&mut self, mode: StepMode, out: &mut impl Write) -> Result<(), RoxError> {
+        self.debugger().set_mode(mode);
+        let chunk = compiler::compile_with(self.source, self.vm.compile_options())?;
+        let result = self.vm.run(chunk);
+        self.report(result, out)
+    }
+
+    /// Resumes a paused script. Stepping commands start it if needed.
+    fn proceed(&mut self, mode: StepMode, out: &mut impl Write) -> Result<(), RoxError> {
+        if !self.running {
+            return match mode {
+                StepMode::Step | StepMode::Next => self.start(mode, out),
+                StepMode::Continue | StepMode::Finish => {
+                    writeln!(out, "The script is not running.")?;
+                    Ok(())
+                }
+            };
+        }
+        self.debugger().set_mode(mode);
+        let result = self.vm.resume();
+        self.report(result, out)
+    }
+
+    /// Executes `count` instructions, starting the script if needed, unless a
+    /// breakpoint comes first.
+    fn step_instructions(&mut self, count: u64, out: &mut impl Write) -> Result<(), RoxError> {
+        self.debugger().set_mode(StepMode::Continue);
+        self.vm.set_step_budget(Some(count));
+        let result = if self.running {
+            self.vm.resume()
+        } else {
+            compiler::compile_with(self.source, self.vm.compile_options())
+                .map_err(RoxError::from)
+                .and_then(|chunk| self.vm.run(chunk))
+        };
+        self.vm.set_step_budget(None);
+        self.report(result, out)
+    }
+
+    fn report(
+        &mut self,
+        result: Result<RunState, RoxError>,
+        out: &mut impl Write,
+    ) -> Result<(), RoxError> {
+        self.running = false;
+        match result {
+            Ok(RunState::Paused(Pause { line, reason })) => {
+                self.running = true;
+                if reason == PauseReason::Breakpoint {
+                    write!(out, "Breakpoint, ")?;
+                }
+                writeln!(out, "line {line}:")?;
+                writeln!(out, "{line:4}  {}", self.source_line(line))?;
+            }
+            // `stepi` ran out of instructions.
+            Ok(RunState::Suspended) => {
+                self.running = true;
+                let line = self.vm.current_line().unwrap_or(1);
+                writeln!(out, "line {line}:")?;
+                writeln!(out, "{line:4}  {}", self.source_line(line))?;
+            }
+            Ok(RunState::Finished) => writeln!(out, "Script finished.")?,
+            Err(e) => writeln!(out, "{e}")?,
+        }
+        Ok(())
+    }
+
+    /// Prints the lines around the current one, marking it.
+    fn list(&mut self, out: &mut impl Write) -> Result<(), RoxError> {
+        let current = self.vm.current_line().unwrap_or(1);
+        let first = current.saturating_sub(2).max(1);
+        for (index, text) in self.source.lines().enumerate().skip(first - 1).take(5) {
+            let line = index + 1;
+            let marker = if line == current { "=>" } else { "  " };
+            writeln!(out, "{marker}{line:4}  {text}")?;
+        }
+        Ok(())
+    }
+
+    fn source_line(&self, line: usize) -> &str {
+        line.checked_sub(1)
+            .and_then(|index| self.source.lines().nth(index))
+            .unwrap_or_default()
+    }
+}
+
+/// Prints one variable per line, or `empty` if there are none.
+fn write_variables(
+    variables: &[(String, Value)],
+    empty: &str,
+    out: &mut impl Write,
+) -> Result<(), RoxError> {
+    if variables.is_empty() {
+        writeln!(out, "{empty}")?;
+    }
+    for (name, value) in variables {
+        writeln!(out, "{name} = {value}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::array::Array;
+    use crate::compiler::CompileOptions;
+    use crate::vm::error::CompileError;
+
+    fn session(source: &str, commands: &str) -> (String, String) {
+        let mut vm = Vm::with_output(Array::default());
+        let mut out = Array::default();
+        run(&mut vm, source, commands.as_bytes(), &mut out).unwrap();
+        let program = String::from_utf8(vm.into_output().to_vec()).unwrap();
+        (program, String::from_utf8(out.to_vec()).unwrap())
+    }
+
+    #[test]
+    fn breakpoints_and_stepping() {
+        let (program, out) = session(
+            "1 +\n2 *\n3",
+            "break 2\nrun\nstack\nstep\nbt\n\ncontinue\nquit\n",
+        );
+        assert_eq!(program, "7\n");
+        let out = out.replace(PROMPT, "");
+        assert_eq!(
+            out,
+            "Breakpoint at line 2.\n\
+             Breakpoint, line 2:\n   2  2 *\n\
+             [ 1 ]\n\
+             line 3:\n   3  3\n\
+             #0  script at line 3\n\
+             #0  script at line 3\n\
+             Script finished.\n"
+        );
+    }
+
+    #[test]
+    fn step_starts_the_script() {
+        let (_, out) = session("1 +\n2", "s\nlist\ninfo b\nd 4\nlocals\nglobals\n");
+        let out = out.replace(PROMPT, "");
+        assert!(out.starts_with("line 1:\n   1  1 +\n=>   1  1 +\n     2  2\n"));
+        assert!(
+            out.ends_with("No breakpoints.\nNo breakpoint at line 4.\n(no locals)\n(no globals)\n")
+        );
+    }
+
+    #[test]
+    fn stepi_executes_instructions() {
+        let (program, out) = session("1 +\n2 *\n3", "si 2\nstack\nsi\nstack\nsi 0\nc\n");
+        assert_eq!(program, "7\n");
+        assert_eq!(
+            out.replace(PROMPT, ""),
+            "line 3:\n   3  3\n\
+             [ 1 2 ]\n\
+             line 3:\n   3  3\n\
+             [ 1 2 3 ]\n\
+             Expected a number of instructions.\n\
+             Script finished.\n"
+        );
+    }
+
+    #[test]
+    fn compile_errors_end_the_session() {
+        let mut vm = Vm::with_output(Array::default());
+        let mut out = Array::default();
+        assert!(run(&mut vm, "(1 +", &b"run\n"[..], &mut out).is_err());
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn compiles_with_the_vm_options() {
+        let mut vm = Vm::with_output(Array::default());
+        vm.set_compile_options(CompileOptions {
+            max_errors: std::num::NonZeroUsize::new(1),
+            ..CompileOptions::default()
+        });
+        match run(&mut vm, "1 +;\n2 *;", &b""[..], &mut Array::default()) {
+            Err(RoxError::Compile(CompileError::Parse(errors))) => assert_eq!(errors.0.length(), 1),
+            other => panic!("expected parse errors, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn missing_source_lines_are_empty() {
+        let mut vm = Vm::with_output(Array::default());
+        let session = Session {
+            vm: &mut vm,
+            source: "1 +\n2",
+            running: false,
+        };
+        assert_eq!(session.source_line(0), "");
+        assert_eq!(session.source_line(2), "2");
+        assert_eq!(session.source_line(3), "");
+    }
+}
@@ -0,0 +1,306 @@
+//! `rox fmt`: a canonical layout for Lox sources.
+//!
+//! The formatter works on the token stream, so it also handles statements the
+//! compiler does not support yet. It puts one statement per line, indents
+//! blocks by two spaces, spaces binary operators, and keeps comments and
+//! single blank lines between statements.
+
+use crate::array::Array;
+use crate::compiler::scanner::{ScanError, Scanner, Token, TokenType};
+
+const INDENT: &str = "  ";
+
+/// Formats `source`. Formatting the output again leaves it unchanged.
+pub fn format(source: &str) -> Result<String, ScanError> {
+    let mut scanner = Scanner::with_comments(source);
+    let mut tokens = Array::default();
+    while let Some(token) = scanner.scan_token() {
+        tokens.push(token?);
+    }
+
+    let mut formatter = Formatter {
+        out: String::new(),
+        indent: 0,
+        parens: 0,
+        line: 0,
+        previous: None,
+        operand: false,
+        unary: false,
+        pending_newline: false,
+    };
+    for token in tokens.iter() {
+        formatter.token(token);
+    }
+    if !formatter.out.is_empty() {
+        formatter.out.push('\n');
+    }
+    Ok(formatter.out)
+}
+
+struct Formatter {
+    out: String,
+    indent: usize,
+    /// Open parentheses, inside which `;` separates `for` clauses.
+    parens: usize,
+    /// The line the previous token ended on.
+    line: usize,
+    previous: Option<TokenType>,
+    /// Whether the previous code token ends an operand, making a `-` binary.
+    operand: bool,
+    /// Whether the previous code token is a prefix operator.
+    unary: bool,
+    pending_newline: bool,
+}
+
+impl Formatter {
+    fn token(&mut self, token: &Token<'_>) {
+        let token_type = token.token_type;
+        let start_line = token.line - token.start.matches('\n').count();
+
+        if token_type == TokenType::Comment {
+            if self.previous.is_some() && start_line == self.line {
+                self.out.push(' ');
+            } else {
+                self.break_line(token_type, start_line);
+            }
+            self.out.push_str(token.start.trim_end());
+            self.finish_token(token);
+            // Code may follow a block comment on the same line.
+            self.pending_newline = token.start.starts_with("//");
+            return;
+        }
+
+        match token_type {
+            TokenType::RightBrace => {
+                self.indent = self.indent.saturating_sub(1);
+                // `{}` stays on one line.
+                self.pending_newline = self.previous != Some(TokenType::LeftBrace);
+            }
+            TokenType::Else if self.previous == Some(TokenType::RightBrace) => {
+                self.pending_newline = false;
+            }
+            _ => {}
+        }
+        if self.previous == Some(TokenType::Comment) && start_line > self.line {
+            self.pending_newline = true;
+        }
+
+        if self.pending_newline {
+            self.break_line(token_type, start_line);
+        } else if self.space_before(token_type) {
+            self.out.push(' ');
+        }
+        self.out.push_str(token.start);
+
+        self.unary = match token_type {
+            TokenType::Bang => true,
+            TokenType::Minus => !self.operand,
+            _ => false,
+        };
+        self.operand = matches!(
+            token_type,
+            TokenType::Identifier
+                | TokenType::Number
+                | TokenType::String
+                | TokenType::RightParen
+                | TokenType::True
+                | TokenType::False
+                | TokenType::Nil
+                | TokenType::This
+        );
+        match token_type {
+            TokenType::LeftParen => self.parens += 1,
+            TokenType::RightParen => self.parens = self.parens.saturating_sub(1),
+            TokenType::LeftBrace => {
+                self.indent += 1;
+                self.pending_newline = true;
+            }
+            TokenType::RightBrace => self.pending_newline = true,
+            TokenType::Semicolon if self.parens == 0 => self.pending_newline = true,
+            _ => {}
+        }
+        self.finish_token(token);
+    }
+
+    fn finish_token(&mut self, token: &Token<'_>) {
+        self.previous = Some(token.token_type);
+        self.line = token.line;
+    }
+
+    /// Starts a new line for a token that begins on `start_line`, keeping one
+    /// blank line if the source had any.
+    fn break_line(&mut self, token_type: TokenType, start_line: usize) {
+        self.pending_newline = false;
+        if self.previous.is_none() {
+            return;
+        }
+        self.out.push('\n');
+        let blank = start_line > self.line + 1
+            && self.previous != Some(TokenType::LeftBrace)
+            && token_type != TokenType::RightBrace;
+        if blank {
+            self.out.push('\n');
+        }
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn space_before(&self, token_type: TokenType) -> bool {
+        let Some(previous) = self.previous else {
+            return false;
+        };
+        match token_type {
+            // `1 .x` must not become the number `1.`.
+            TokenType::Dot => previous == TokenType::Number,
+            TokenType::RightParen
+            | TokenType::RightBrace
+            | TokenType::Comma
+            | TokenType::Semicolon => false,
+            // `!=` would scan as one token.
+            TokenType::Equal | TokenType::EqualEqual if self.unary => true,
+            _ if self.unary => false,
+            _ if matches!(previous, TokenType::LeftParen | TokenType::Dot) => false,
+            // A call.
+            TokenType::LeftParen => !matches!(
+                previous,
+                TokenType::Identifier | TokenType::RightParen | TokenType::This
+            ),
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn formatted(source: &str) -> String {
+        format(source).unwrap()
+    }
+
+    #[test]
+    fn spaces_operators() {
+        assert_eq!(formatted("(1+2)*-3/ -(4)"), "(1 + 2) * -3 / -(4)\n");
+        assert_eq!(formatted("a.b(c,d)  .e"), "a.b(c, d).e\n");
+        assert_eq!(formatted("!x==!=y"), "!x == != y\n");
+    }
+
+    #[test]
+    fn one_statement_per_line() {
+        assert_eq!(
+            formatted("var x=1;print x;if(x){print x;}else{}"),
+            "var x = 1;\nprint x;\nif (x) {\n  print x;\n} else {}\n"
+        );
+        assert_eq!(
+            formatted("for(var i=0;i<3;i=i+1)print i;"),
+            "for (var i = 0; i < 3; i = i + 1) print i;\n"
+        );
+    }
+
+    #[test]
+    fn indents_blocks() {
+        assert_eq!(
+            formatted("class A<B{init(x){this.x=x;}\nget(){return this.x;}}"),
+            "class A < B {\n  init(x) {\n    this.x = x;\n  }\n  get() {\n    return this.x;\n  }\n}\n"
+        );
+    }
+
+    #[test]
+    fn keeps_comments_and_blank_lines() {
+        let source =
+            "// header\n\n\n\nvar a = 1;  // one\n\n{\n\n// inside\nprint a;\n\n}\n1 + // two\n2";
+        assert_eq!(
+            formatted(source),
+            "// header\n\nvar a = 1; // one\n\n{\n  // inside\n  print a;\n}\n1 + // two\n2\n"
+        );
+    }
+
+    #[test]
+    fn keeps_block_comments() {
+        assert_eq!(formatted("1+/* a */2"), "1 + /* a */ 2\n");
+        let source = "/* header\n   /* nested */ */\n{\nprint 1;/* one */\n}";
+        assert_eq!(
+            formatted(source),
+            "/* header\n   /* nested */ */\n{\n  print 1; /* one */\n}\n"
+        );
+    }
+
+    #[test]
+    fn empty_source() {
+        assert_eq!(formatted(""), "");
+        assert_eq!(formatted("  \n"), "");
+    }
+
+    #[test]
+    fn scan_errors_are_reported() {
+        assert!(format("\"open").is_err());
+    }
+
+    fn code_tokens(source: &str) -> Array<(TokenType, String)> {
+        let mut scanner = Scanner::with_comments(source);
+        std::iter::from_fn(|| scanner.scan_token())
+            .map(|token| {
+                let token = token.unwrap();
+                (token.token_type, token.start.trim_end().to_owned())
+            })
+            .collect()
+    }
+
+    fn source() -> impl Strategy<Value = String> {
+        let words = [
+            "var",
+            "x",
+            "y",
+            "=",
+            "1",
+            "2.5",
+            "\"s\"",
+            "\"a\nb\"",
+            "+",
+            "-",
+            "*",
+            "/",
+            "!",
+            "==",
+            "!=",
+            "<",
+            "(",
+            ")",
+            "{",
+            "}",
+            ";",
+            ",",
+            ".",
+            "if",
+            "else",
+            "fun",
+            "return",
+            "print",
+            "this",
+            "class",
+            "// note\n",
+            "/* a */",
+            "/* a\n/* b */ */",
+            "\n",
+            "\n\n",
+        ];
+        proptest::collection::vec(proptest::sample::select(words.to_vec()), 0..40)
+            .prop_map(|words| words.join(" "))
+    }
+
+    proptest! {
+        #[test]
+        fn formatting_is_idempotent(source in source()) {
+            let once = formatted(&source);
+            prop_assert_eq!(formatted(&once), once);
+        }
+
+        #[test]
+        fn formatting_keeps_tokens(source in source()) {
+            prop_assert_eq!(code_tokens(&formatted(&source)), code_tokens(&source));
+        }
+    }
+}
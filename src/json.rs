@@ -0,0 +1,455 @@
+//! Just enough JSON to emit machine-readable output, and to read the
+//! messages of editor protocols, without extra dependencies.
+
+use std::fmt::{self, Display, Formatter};
+
+use thiserror::Error;
+
+use crate::array::Array;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Array<Json>),
+    Object(Array<(String, Json)>),
+}
+
+#[allow(dead_code)]
+impl Json {
+    pub fn object<const N: usize>(fields: [(&str, Json); N]) -> Self {
+        let mut object = Array::default();
+        for (key, value) in fields {
+            object.push((key.to_owned(), value));
+        }
+        Self::Object(object)
+    }
+
+    pub fn string(s: impl Into<String>) -> Self {
+        Self::String(s.into())
+    }
+
+    /// Adds a field to an object. Other values are returned unchanged.
+    pub fn with(mut self, key: &str, value: impl Into<Json>) -> Self {
+        if let Self::Object(fields) = &mut self {
+            fields.push((key.to_owned(), value.into()));
+        }
+        self
+    }
+
+    pub fn parse(text: &str) -> Result<Self, JsonError> {
+        let mut parser = Parser {
+            bytes: text.as_bytes(),
+            offset: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.offset < parser.bytes.len() {
+            return Err(parser.error("trailing characters"));
+        }
+        Ok(value)
+    }
+
+    /// The value of `key` if this is an object that has it.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Self::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// The value as a non-negative integer, if it is one.
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64()
+            .filter(|n| n.fract() == 0.0 && *n >= 0.0)
+            .map(|n| n as usize)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Self::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("Invalid JSON at byte {offset}: {message}.")]
+pub struct JsonError {
+    pub offset: usize,
+    pub message: &'static str,
+}
+
+/// A recursive descent parser over the bytes of a JSON document.
+struct Parser<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &'static str) -> JsonError {
+        JsonError {
+            offset: self.offset,
+            message,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.offset).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.offset += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8, message: &'static str) -> Result<(), JsonError> {
+        self.skip_whitespace();
+        if self.peek() != Some(byte) {
+            return Err(self.error(message));
+        }
+        self.offset += 1;
+        Ok(())
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, JsonError> {
+        if !self.bytes[self.offset..].starts_with(word.as_bytes()) {
+            return Err(self.error("unknown literal"));
+        }
+        self.offset += word.len();
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, JsonError> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Err(self.error("unexpected end of input")),
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b'[') => self.array(),
+            Some(b'{') => self.object(),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("expected a value")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let start = self.offset;
+        while matches!(
+            self.peek(),
+            Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+        ) {
+            self.offset += 1;
+        }
+        // The characters checked above are all ASCII.
+        let text = std::str::from_utf8(&self.bytes[start..self.offset]).unwrap_or_default();
+        text.parse().map(Json::Number).map_err(|_| JsonError {
+            offset: start,
+            message: "invalid number",
+        })
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect(b'"', "expected a string")?;
+        let mut bytes: Array<u8> = Array::default();
+        loop {
+            let Some(byte) = self.peek() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.offset += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escape = self
+                        .peek()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    self.offset += 1;
+                    let unescaped = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    let mut buffer = [0; 4];
+                    for &byte in unescaped.encode_utf8(&mut buffer).as_bytes() {
+                        bytes.push(byte);
+                    }
+                }
+                byte => bytes.push(byte),
+            }
+        }
+        String::from_utf8(bytes.to_vec()).map_err(|_| self.error("invalid UTF-8"))
+    }
+
+    /// Decodes the digits of a `\u` escape, combining surrogate pairs.
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let high = self.hex4()?;
+        let code = if (0xd800..0xdc00).contains(&high) {
+            if !self.bytes[self.offset..].starts_with(b"\\u") {
+                return Err(self.error("unpaired surrogate"));
+            }
+            self.offset += 2;
+            let low = self.hex4()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.error("unpaired surrogate"));
+            }
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let digits = self
+            .bytes
+            .get(self.offset..self.offset + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("invalid escape"))?;
+        self.offset += 4;
+        Ok(digits)
+    }
+
+    fn array(&mut self) -> Result<Json, JsonError> {
+        self.expect(b'[', "expected an array")?;
+        let mut items = Array::default();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.offset += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.offset += 1,
+                Some(b']') => {
+                    self.offset += 1;
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, JsonError> {
+        self.expect(b'{', "expected an object")?;
+        let mut fields = Array::default();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.offset += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(b':', "expected ':'")?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.offset += 1,
+                Some(b'}') => {
+                    self.offset += 1;
+                    return Ok(Json::Object(fields));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+}
+
+impl From<usize> for Json {
+    fn from(n: usize) -> Self {
+        Self::Number(n as f64)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Self::string(s)
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Self::String(s)
+    }
+}
+
+impl From<Array<Json>> for Json {
+    fn from(items: Array<Json>) -> Self {
+        Self::Array(items)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(n: f64) -> Self {
+        Self::Number(n)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Bool(b) => write!(f, "{b}"),
+            // JSON has no representation for NaN or the infinities.
+            Self::Number(n) if !n.is_finite() => write!(f, "null"),
+            Self::Number(n) => write!(f, "{n}"),
+            Self::String(s) => write_escaped(f, s),
+            Self::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                write!(f, "]")
+            }
+            Self::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_escaped(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_escaped(f: &mut Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if u32::from(c) < 0x20 => write!(f, "\\u{:04x}", u32::from(c))?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn serializes_nested_values() {
+        let mut items = Array::default();
+        items.push(Json::from(1.5));
+        items.push(Json::Null);
+        items.push(Json::Bool(true));
+        let json = Json::object([
+            ("name", Json::string("a\"b\n")),
+            ("items", Json::Array(items)),
+        ]);
+        assert_eq!(
+            json.to_string(),
+            r#"{"name":"a\"b\n","items":[1.5,null,true]}"#
+        );
+    }
+
+    #[test]
+    fn integers_have_no_fraction_and_nan_is_null() {
+        assert_eq!(Json::from(42usize).to_string(), "42");
+        assert_eq!(Json::from(f64::NAN).to_string(), "null");
+        assert_eq!(Json::string("\u{1}").to_string(), r#""\u0001""#);
+    }
+
+    #[test]
+    fn parses_nested_values() {
+        let json =
+            Json::parse(r#" {"a": [1, -2.5e1, true, null], "b": {"c": "d\"é😀"}} "#).unwrap();
+        let items = json.get("a").and_then(Json::as_array).unwrap();
+        assert_eq!(items[0].as_usize(), Some(1));
+        assert_eq!(items[1].as_f64(), Some(-25.0));
+        assert_eq!(items[2].as_bool(), Some(true));
+        assert!(matches!(items[3], Json::Null));
+        let c = json
+            .get("b")
+            .and_then(|b| b.get("c"))
+            .and_then(Json::as_str);
+        assert_eq!(c, Some("d\"é😀"));
+        assert!(json.get("z").is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert_eq!(
+            Json::parse("[1,").unwrap_err(),
+            JsonError {
+                offset: 3,
+                message: "unexpected end of input"
+            }
+        );
+        assert!(Json::parse(r#"{"a" 1}"#).is_err());
+        assert!(Json::parse("tru").is_err());
+        assert!(Json::parse("1 2").is_err());
+        assert!(Json::parse(r#""\ud800""#).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_strings_round_trip(s in ".*") {
+            let json = Json::string(s.clone()).to_string();
+            let parsed = Json::parse(&json).unwrap();
+            prop_assert_eq!(parsed.as_str(), Some(s.as_str()));
+        }
+    }
+}
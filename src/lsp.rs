@@ -0,0 +1,517 @@
+//! `rox lsp`: a Language Server Protocol server over stdin and stdout.
+//!
+//! Documents are fully resynchronised on every change. Diagnostics come from
+//! the compiler and the lints, semantic tokens from the scanner. Lox has no
+//! declarations yet, so there are no symbols to navigate: definitions,
+//! references, hover and document symbols wait for the grammar to have them.
+
+use std::io::{self, BufRead, Write};
+
+use crate::array::Array;
+use crate::compiler::lint;
+use crate::compiler::scanner::{self, Scanner, TokenType};
+use crate::compiler::{self, CompileError, CompileOptions, Severity, Span};
+use crate::json::Json;
+use crate::transport;
+
+/// The semantic token types, indexed by the numbers sent to the client.
+const TOKEN_TYPES: [&str; 5] = ["keyword", "variable", "string", "number", "operator"];
+
+const METHOD_NOT_FOUND: f64 = -32601.0;
+const INVALID_PARAMS: f64 = -32602.0;
+
+/// Serves requests from `input` until the client sends `exit` or the input ends.
+pub fn run(mut input: impl BufRead, output: impl Write) -> io::Result<()> {
+    let mut server = Server {
+        output,
+        documents: Array::default(),
+    };
+    while let Some(message) = transport::read_message(&mut input)? {
+        if !server.handle(&message)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+struct Document {
+    uri: String,
+    text: String,
+}
+
+struct Server<W: Write> {
+    output: W,
+    documents: Array<Document>,
+}
+
+impl<W: Write> Server<W> {
+    /// Handles one message. Returns `false` once the client asks to exit.
+    fn handle(&mut self, message: &Json) -> io::Result<bool> {
+        let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = message.get("params").unwrap_or(&Json::Null);
+        let Some(id) = message.get("id") else {
+            return self.notification(method, params);
+        };
+
+        let result = match method {
+            "initialize" => Some(capabilities()),
+            "shutdown" => Some(Json::Null),
+            "textDocument/semanticTokens/full" => self
+                .document(params)
+                .map(|document| semantic_tokens(&document.text)),
+            _ => {
+                let message = format!("Unsupported method '{method}'.");
+                return self.error(id, METHOD_NOT_FOUND, &message).map(|()| true);
+            }
+        };
+        match result {
+            Some(result) => self.respond(id, result)?,
+            None => self.error(id, INVALID_PARAMS, "Unknown document.")?,
+        }
+        Ok(true)
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> io::Result<bool> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str)
+            .unwrap_or("")
+            .to_owned();
+        match method {
+            "exit" => return Ok(false),
+            "textDocument/didOpen" => {
+                let text = params
+                    .get("textDocument")
+                    .and_then(|document| document.get("text"))
+                    .and_then(Json::as_str)
+                    .unwrap_or("");
+                self.update(uri, text.to_owned())?;
+            }
+            "textDocument/didChange" => {
+                // Full synchronisation: the last change holds the whole text.
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Json::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str);
+                if let Some(text) = text {
+                    self.update(uri, text.to_owned())?;
+                }
+            }
+            "textDocument/didClose" => {
+                if let Some(index) = self.documents.iter().position(|d| d.uri == uri) {
+                    self.documents.remove(index);
+                }
+                self.publish_diagnostics(&uri, Array::default())?;
+            }
+            // `initialized`, `$/cancelRequest` and the like need no answer.
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn update(&mut self, uri: String, text: String) -> io::Result<()> {
+        let diagnostics = diagnostics(&text);
+        match self.documents.iter().position(|d| d.uri == uri) {
+            Some(index) => self.documents.get_mut(index).unwrap().text = text,
+            None => self.documents.push(Document {
+                uri: uri.clone(),
+                text,
+            }),
+        }
+        self.publish_diagnostics(&uri, diagnostics)
+    }
+
+    fn document(&self, params: &Json) -> Option<&Document> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str)?;
+        self.documents.iter().find(|document| document.uri == uri)
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str, diagnostics: Array<Json>) -> io::Result<()> {
+        let params = Json::object([("uri", uri.into()), ("diagnostics", diagnostics.into())]);
+        let notification = Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", "textDocument/publishDiagnostics".into()),
+            ("params", params),
+        ]);
+        transport::write_message(&mut self.output, &notification)
+    }
+
+    fn respond(&mut self, id: &Json, result: Json) -> io::Result<()> {
+        let response = Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("id", id.clone()),
+            ("result", result),
+        ]);
+        transport::write_message(&mut self.output, &response)
+    }
+
+    fn error(&mut self, id: &Json, code: f64, message: &str) -> io::Result<()> {
+        let error = Json::object([("code", code.into()), ("message", message.into())]);
+        let response = Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("id", id.clone()),
+            ("error", error),
+        ]);
+        transport::write_message(&mut self.output, &response)
+    }
+}
+
+fn capabilities() -> Json {
+    let strings = |names: &[&str]| Json::Array(names.iter().map(|&name| name.into()).collect());
+    let legend = Json::object([
+        ("tokenTypes", strings(&TOKEN_TYPES)),
+        ("tokenModifiers", strings(&[])),
+    ]);
+    let capabilities = Json::object([
+        // Full document synchronisation.
+        ("textDocumentSync", 1usize.into()),
+        (
+            "semanticTokensProvider",
+            Json::object([("legend", legend), ("full", true.into())]),
+        ),
+    ]);
+    let server_info = Json::object([
+        ("name", "rox".into()),
+        ("version", env!("CARGO_PKG_VERSION").into()),
+    ]);
+    Json::object([("capabilities", capabilities), ("serverInfo", server_info)])
+}
+
+/// Compiles and lints `text`. Errors cover the token they were found at.
+fn diagnostics(text: &str) -> Array<Json> {
+    let index = LineIndex::new(text);
+    // Only code that parses is linted, as in `rox check --lint`.
+    let mut warnings = Array::default();
+    let result = compiler::parse_with(text, &CompileOptions::default()).and_then(|program| {
+        warnings = lint::lint(&program, &[]);
+        compiler::generate(&program)
+    });
+    let mut found: Array<(Span, Severity, String, Option<&str>)> = match result {
+        Ok(_) => Array::default(),
+        Err(CompileError::Parse(errors)) => errors
+            .iter()
+            .map(|error| {
+                let message = error.message().to_owned();
+                let code = Some(error.code.name());
+                (error.span, error.severity(), message, code)
+            })
+            .collect(),
+        Err(CompileError::Scan(error)) => {
+            let message = error.code.message().to_owned();
+            let code = Some(error.code.name());
+            [(error.span, Severity::Error, message, code)]
+                .into_iter()
+                .collect()
+        }
+        // Other errors have no location.
+        Err(error) => [(index.line_span(0), Severity::Error, error.to_string(), None)]
+            .into_iter()
+            .collect(),
+    };
+    for warning in warnings.iter() {
+        let message = warning.message.clone();
+        found.push((
+            warning.span,
+            warning.severity(),
+            message,
+            Some(warning.lint.name()),
+        ));
+    }
+
+    found
+        .iter()
+        .map(|(span, severity, message, code)| {
+            let severity: usize = match severity {
+                Severity::Error => 1,
+                Severity::Warning => 2,
+            };
+            let diagnostic = Json::object([
+                ("range", index.range(*span)),
+                ("severity", severity.into()),
+                ("source", "rox".into()),
+                ("message", message.as_str().into()),
+            ]);
+            match code {
+                Some(code) => diagnostic.with("code", *code),
+                None => diagnostic,
+            }
+        })
+        .collect()
+}
+
+/// Classifies every token, encoded as LSP's relative five-number groups.
+fn semantic_tokens(text: &str) -> Json {
+    let index = LineIndex::new(text);
+    let mut data = Array::default();
+    let (mut previous_line, mut previous_start) = (0, 0);
+
+    let mut scanner = Scanner::new(text);
+    while let Some(token) = scanner.scan_token() {
+        let Ok(token) = token else { continue };
+        let start = scanner::offset_of(text, &token);
+        let token_type = match token.token_type {
+            TokenType::Identifier => 1,
+            TokenType::String => 2,
+            TokenType::Number => 3,
+            TokenType::Minus
+            | TokenType::Plus
+            | TokenType::Slash
+            | TokenType::Star
+            | TokenType::Bang
+            | TokenType::BangEqual
+            | TokenType::Equal
+            | TokenType::EqualEqual
+            | TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Less
+            | TokenType::LessEqual => 4,
+            TokenType::And
+            | TokenType::Class
+            | TokenType::Else
+            | TokenType::False
+            | TokenType::For
+            | TokenType::Fun
+            | TokenType::If
+            | TokenType::Nil
+            | TokenType::Or
+            | TokenType::Print
+            | TokenType::Return
+            | TokenType::Super
+            | TokenType::This
+            | TokenType::True
+            | TokenType::Var
+            | TokenType::While => 0,
+            _ => continue,
+        };
+
+        let (line, character) = index.position(start);
+        // Tokens may not span lines, so a multi-line string is cut at the first.
+        let first_line = token.start.lines().next().unwrap_or_default();
+        let length = utf16_len(first_line);
+        let delta_start = if line == previous_line {
+            character - previous_start
+        } else {
+            character
+        };
+        for value in [line - previous_line, delta_start, length, token_type, 0] {
+            data.push(Json::from(value));
+        }
+        (previous_line, previous_start) = (line, character);
+    }
+    Json::object([("data", data.into())])
+}
+
+/// Converts between byte offsets and LSP positions, whose characters count
+/// UTF-16 code units.
+struct LineIndex<'a> {
+    text: &'a str,
+    line_starts: Array<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(text: &'a str) -> Self {
+        let mut line_starts: Array<usize> = [0].into_iter().collect();
+        for (offset, byte) in text.bytes().enumerate() {
+            if byte == b'\n' {
+                line_starts.push(offset + 1);
+            }
+        }
+        Self { text, line_starts }
+    }
+
+    /// The zero-based line and character of a byte offset.
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[line];
+        (line, utf16_len(&self.text[start..offset]))
+    }
+
+    /// The span of a zero-based line, without its line break.
+    fn line_span(&self, line: usize) -> Span {
+        let start = self
+            .line_starts
+            .get(line)
+            .copied()
+            .unwrap_or(self.text.len());
+        let end = self.text[start..]
+            .find('\n')
+            .map_or(self.text.len(), |end| start + end);
+        Span {
+            start,
+            end,
+            line: line + 1,
+            column: 1,
+        }
+    }
+
+    fn range(&self, span: Span) -> Json {
+        let position = |offset| {
+            let (line, character) = self.position(offset);
+            Json::object([("line", line.into()), ("character", character.into())])
+        };
+        Json::object([("start", position(span.start)), ("end", position(span.end))])
+    }
+}
+
+fn utf16_len(s: &str) -> usize {
+    s.chars().map(char::len_utf16).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URI: &str = "file:///test.lox";
+
+    /// Runs the server over `messages`, numbering requests from 1, and
+    /// returns everything it sent.
+    fn exchange<const N: usize>(messages: [(&str, Json); N]) -> Array<Json> {
+        let mut input = Array::default();
+        let mut id = 0;
+        for (method, params) in messages {
+            let mut message = Json::object([
+                ("jsonrpc", "2.0".into()),
+                ("method", method.into()),
+                ("params", params),
+            ]);
+            if !method.starts_with("textDocument/did") && method != "exit" {
+                id += 1;
+                message = message.with("id", id);
+            }
+            transport::write_message(&mut input, &message).unwrap();
+        }
+
+        let mut output = Array::default();
+        let input: &[u8] = &input;
+        run(input, &mut output).unwrap();
+        let mut reader: &[u8] = &output;
+        let mut replies = Array::default();
+        while let Some(reply) = transport::read_message(&mut reader).unwrap() {
+            replies.push(reply);
+        }
+        replies
+    }
+
+    fn open(text: &str) -> (&'static str, Json) {
+        let document = Json::object([
+            ("uri", URI.into()),
+            ("languageId", "lox".into()),
+            ("text", text.into()),
+        ]);
+        (
+            "textDocument/didOpen",
+            Json::object([("textDocument", document)]),
+        )
+    }
+
+    fn start_of(range: &Json) -> (usize, usize) {
+        position(range, "start")
+    }
+
+    fn end_of(range: &Json) -> (usize, usize) {
+        position(range, "end")
+    }
+
+    fn position(range: &Json, which: &str) -> (usize, usize) {
+        let position = range.get(which).unwrap();
+        let field = |name| position.get(name).and_then(Json::as_usize).unwrap();
+        (field("line"), field("character"))
+    }
+
+    #[test]
+    fn publishes_diagnostics_on_every_edit() {
+        let change = |text: &str| {
+            let changes: Array<Json> = [Json::object([("text", text.into())])]
+                .into_iter()
+                .collect();
+            (
+                "textDocument/didChange",
+                Json::object([
+                    ("textDocument", Json::object([("uri", URI.into())])),
+                    ("contentChanges", changes.into()),
+                ]),
+            )
+        };
+        let replies = exchange([
+            ("initialize", Json::object([])),
+            open("1 +\n  )"),
+            change("1 + 2"),
+            change("1 /\n  0"),
+            change("var x = 1;"),
+            ("textDocument/hover", Json::Null),
+        ]);
+
+        // Only diagnostics and semantic tokens are offered.
+        let capabilities = replies[0]
+            .get("result")
+            .and_then(|r| r.get("capabilities"))
+            .unwrap();
+        assert!(capabilities.get("semanticTokensProvider").is_some());
+        assert!(capabilities.get("hoverProvider").is_none());
+
+        let diagnostics = |index: usize| {
+            replies[index]
+                .get("params")
+                .and_then(|p| p.get("diagnostics"))
+                .and_then(Json::as_array)
+                .unwrap()
+        };
+        let first = &diagnostics(1)[0];
+        assert_eq!(
+            first.get("message").and_then(Json::as_str),
+            Some("Expect expression.")
+        );
+        assert_eq!(start_of(first.get("range").unwrap()), (1, 2));
+        assert_eq!(end_of(first.get("range").unwrap()), (1, 3));
+        assert!(diagnostics(2).is_empty());
+
+        let warning = &diagnostics(3)[0];
+        assert_eq!(
+            warning.get("code").and_then(Json::as_str),
+            Some("division-by-zero")
+        );
+        assert_eq!(start_of(warning.get("range").unwrap()), (1, 2));
+
+        // Code the compiler rejects is not linted.
+        assert_eq!(diagnostics(4).len(), 1);
+
+        let error = replies[5]
+            .get("error")
+            .and_then(|e| e.get("code"))
+            .and_then(Json::as_f64);
+        assert_eq!(error, Some(METHOD_NOT_FOUND));
+    }
+
+    #[test]
+    fn semantic_tokens_are_relative() {
+        let tokens = semantic_tokens("var s = \"é\" + x;\nfun f(p) { p; }");
+        let data: Vec<usize> = tokens
+            .get("data")
+            .and_then(Json::as_array)
+            .unwrap()
+            .iter()
+            .map(|n| n.as_usize().unwrap())
+            .collect();
+        assert_eq!(
+            data,
+            [
+                0, 0, 3, 0, 0, // var
+                0, 4, 1, 1, 0, // s
+                0, 2, 1, 4, 0, // =
+                0, 2, 3, 2, 0, // "é"
+                0, 4, 1, 4, 0, // +, after one UTF-16 unit for é
+                0, 2, 1, 1, 0, // x
+                1, 0, 3, 0, 0, // fun
+                0, 4, 1, 1, 0, // f
+                0, 2, 1, 1, 0, // p
+                0, 5, 1, 1, 0, // p
+            ]
+        );
+    }
+}
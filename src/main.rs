@@ -1,32 +1,139 @@
 mod array;
+mod cli;
 mod compiler;
+mod dap;
+mod debug;
+mod formatter;
+mod json;
+mod lsp;
+mod repl;
+mod test_runner;
+mod transport;
 mod vm;
 
-use std::io::{self, BufRead, Write};
+use std::io::{self, Read, Write};
 use std::process::ExitCode;
 
+use array::Array;
+use cli::{Cli, Command, DisasmFormat, Input};
+use compiler::lint::{self, Lint};
+use compiler::{CompileOptions, Diagnostic, ErrorCode, ErrorFormat, Severity};
 use vm::Vm;
-use vm::error::RoxError;
+use vm::chunk::Chunk;
+use vm::disassembler::Disassembler;
+use vm::error::{CompileError, ParseErrorReport, RoxError};
+use vm::serialize;
 
 fn main() -> ExitCode {
+    let cli = match Cli::parse(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(e) => {
+            let options = CompileOptions::default();
+            return report(Err(RoxError::Usage(e.to_string())), &options, "");
+        }
+    };
+
     let mut vm = Vm::default();
+    vm.set_print_code(cli.print_code);
+    vm.set_trace_execution(cli.trace_execution);
+    if let Some(max_stack) = cli.max_stack {
+        vm.set_max_stack_size(max_stack);
+    }
+    vm.set_limits(cli.limits);
+    let options = cli.compile_options;
+    vm.set_compile_options(options);
+    let file = source_name(&cli.command);
 
-    let path = std::env::args().skip(1).find(|arg| !arg.starts_with("--"));
-    let result = match path.as_deref() {
-        None => repl(&mut vm),
-        Some(path) => run_file(&mut vm, path),
+    let result = match cli.command {
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            Ok(())
+        }
+        Command::Version => {
+            println!("rox {}", env!("CARGO_PKG_VERSION"));
+            Ok(())
+        }
+        Command::Repl => repl::run(&mut vm),
+        Command::Run { input } => run(&mut vm, &input),
+        Command::Compile { input, output } => compile(&input, output.as_deref(), &options),
+        Command::Disasm { input, format } => disasm(&input, format, &options),
+        Command::Check { input, lint, allow } => check(&input, lint, &allow, &options),
+        Command::Debug { input } => debug(&mut vm, &input),
+        Command::Dap => dap::run(io::stdin().lock(), io::stdout().lock()).map_err(RoxError::from),
+        Command::Lsp => lsp::run(io::stdin().lock(), io::stdout().lock()).map_err(RoxError::from),
+        Command::Fmt { input, check } => match fmt(&input, check) {
+            Ok(true) => Ok(()),
+            Ok(false) => return ExitCode::FAILURE,
+            Err(e) => Err(e),
+        },
+        Command::Explain { code } => {
+            explain(code);
+            Ok(())
+        }
+        Command::Test { paths } => match test_runner::run(&paths, &mut io::stdout()) {
+            Ok(true) => Ok(()),
+            Ok(false) => return ExitCode::FAILURE,
+            Err(e) => Err(e.into()),
+        },
     };
+    report(result, &options, &file)
+}
 
+/// The name diagnostics use for the source `command` reads.
+fn source_name(command: &Command) -> String {
+    match command {
+        Command::Run { input, .. }
+        | Command::Disasm { input, .. }
+        | Command::Check { input, .. }
+        | Command::Debug { input }
+        | Command::Fmt { input, .. } => input.to_string(),
+        Command::Compile { input, .. } => input.clone(),
+        _ => String::new(),
+    }
+}
+
+fn report(result: Result<(), RoxError>, options: &CompileOptions, file: &str) -> ExitCode {
+    let format = options.error_format;
     match result {
         Ok(()) => ExitCode::SUCCESS,
+        Err(RoxError::Usage(e)) => {
+            eprintln!("{e}");
+            ExitCode::from(64)
+        }
         Err(RoxError::Io(e)) => {
             eprintln!("{e}");
             ExitCode::from(74)
         }
-        Err(RoxError::Compile(e)) => {
+        Err(RoxError::Compile(CompileError::Parse(ParseErrorReport(errors))))
+            if format != ErrorFormat::Human =>
+        {
+            eprintln!("{}", compiler::format_parse_errors(&errors, format, file));
+            ExitCode::from(65)
+        }
+        // The warnings themselves have been reported already.
+        Err(RoxError::Compile(CompileError::Warnings { .. })) if format != ErrorFormat::Human => {
+            ExitCode::from(65)
+        }
+        Err(RoxError::Compile(e @ CompileError::Parse(_))) => {
             eprintln!("{e}");
             ExitCode::from(65)
         }
+        Err(RoxError::Compile(CompileError::Scan(e))) => {
+            eprintln!("{}", Diagnostic::from(&e).render(format, file));
+            ExitCode::from(65)
+        }
+        Err(RoxError::Compile(e)) => {
+            let diagnostic = Diagnostic {
+                code: e.code().map(|code| code.name().to_owned()),
+                ..Diagnostic::new(Severity::Error, e.to_string())
+            };
+            eprintln!("{}", diagnostic.render(format, file));
+            ExitCode::from(65)
+        }
+        Err(RoxError::Runtime(e)) if format != ErrorFormat::Human => {
+            eprintln!("{}", e.diagnostic().render(format, file));
+            ExitCode::from(70)
+        }
         Err(RoxError::Runtime(e)) => {
             eprintln!("{e}");
             ExitCode::from(70)
@@ -34,25 +141,145 @@ fn main() -> ExitCode {
     }
 }
 
-fn repl(vm: &mut Vm) -> Result<(), RoxError> {
-    let stdin = io::stdin();
-    loop {
-        print!("> ");
-        io::stdout().flush()?;
-
-        let mut line = String::new();
-        if stdin.lock().read_line(&mut line)? == 0 {
-            println!();
-            break;
+fn read_bytes(input: &Input) -> io::Result<Vec<u8>> {
+    match input {
+        Input::File(path) => std::fs::read(path),
+        Input::Stdin => {
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes)?;
+            Ok(bytes)
         }
+        Input::Inline(source) => Ok(source.clone().into_bytes()),
+    }
+}
 
-        vm.interpret(&line)?;
+fn into_source(bytes: Vec<u8>) -> io::Result<String> {
+    String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Compiles `input`, or loads it if it is already bytecode.
+fn load(input: &Input, options: &CompileOptions) -> Result<(Chunk, Option<String>), RoxError> {
+    let bytes = read_bytes(input)?;
+    if serialize::is_bytecode(&bytes) {
+        let chunk = Chunk::load(&bytes).map_err(CompileError::from)?;
+        return Ok((chunk, None));
     }
+
+    let source = into_source(bytes)?;
+    let chunk = compiler::compile_with(&source, options)?;
+    Ok((chunk, Some(source)))
+}
+
+fn run(vm: &mut Vm, input: &Input) -> Result<(), RoxError> {
+    let (chunk, _) = load(input, vm.compile_options())?;
+    vm.run(chunk)?;
+    Ok(())
+}
+
+/// `rox compile <input> [-o <output>]`: writes the compiled chunk as `.roxc`.
+fn compile(input: &str, output: Option<&str>, options: &CompileOptions) -> Result<(), RoxError> {
+    let output = match output {
+        Some(output) => output.into(),
+        None => std::path::Path::new(input).with_extension("roxc"),
+    };
+
+    let source = std::fs::read_to_string(input)?;
+    let chunk = compiler::compile_with(&source, options)?;
+
+    let mut file = io::BufWriter::new(std::fs::File::create(output)?);
+    chunk.serialize(&mut file)?;
+    file.flush()?;
     Ok(())
 }
 
-fn run_file(vm: &mut Vm, path: &str) -> Result<(), RoxError> {
-    let source = std::fs::read_to_string(path)?;
-    vm.interpret(&source)?;
+fn disasm(input: &Input, format: DisasmFormat, options: &CompileOptions) -> Result<(), RoxError> {
+    let (chunk, source) = load(input, options)?;
+    let name = input.to_string();
+    let disassembler = Disassembler::new(&chunk, &name);
+
+    let mut stdout = io::stdout().lock();
+    match format {
+        DisasmFormat::Text => disassembler.write(&mut stdout)?,
+        DisasmFormat::Json => disassembler.write_json(&mut stdout)?,
+        DisasmFormat::Source => {
+            disassembler.write_annotated(&mut stdout, source.as_deref().unwrap_or_default())?
+        }
+    }
+    Ok(())
+}
+
+/// `rox debug <script>`: debugger commands are read from stdin.
+fn debug(vm: &mut Vm, input: &Input) -> Result<(), RoxError> {
+    if *input == Input::Stdin {
+        return Err(RoxError::Usage(
+            "The debugger reads commands from stdin, so the script must be a file.".to_owned(),
+        ));
+    }
+    let source = into_source(read_bytes(input)?)?;
+    debug::run(vm, &source, io::stdin().lock(), &mut io::stdout())
+}
+
+/// `rox fmt`: rewrites a script file, or prints formatted stdin or inline
+/// source. With `check`, only reports whether the input is formatted.
+fn fmt(input: &Input, check: bool) -> Result<bool, RoxError> {
+    let source = into_source(read_bytes(input)?)?;
+    let formatted = formatter::format(&source).map_err(CompileError::from)?;
+    if check {
+        if formatted != source {
+            eprintln!("{input} is not formatted.");
+        }
+        return Ok(formatted == source);
+    }
+    match input {
+        Input::File(path) if formatted != source => std::fs::write(path, formatted)?,
+        Input::File(_) => {}
+        Input::Stdin | Input::Inline(_) => print!("{formatted}"),
+    }
+    Ok(true)
+}
+
+/// `rox explain`: the explanation of `code`, or a list of every code.
+fn explain(code: Option<ErrorCode>) {
+    match code {
+        Some(code) => println!("{code}: {}\n\n{}", code.message(), code.explanation()),
+        None => {
+            for code in ErrorCode::ALL {
+                println!("{code}  {}", code.message());
+            }
+        }
+    }
+}
+
+/// `rox check`: compiles without running. With `lint`, a script that parses
+/// is also linted; warnings only fail the check as errors with
+/// `warnings_as_errors`.
+fn check(
+    input: &Input,
+    lint: bool,
+    allow: &[Lint],
+    options: &CompileOptions,
+) -> Result<(), RoxError> {
+    let source = into_source(read_bytes(input)?)?;
+    // The syntax tree front-end reports the same errors as `compile_with`.
+    let program = compiler::parse_with(&source, options)?;
+    let warnings = if lint {
+        lint::lint(&program, allow)
+    } else {
+        Array::default()
+    };
+    let file = input.to_string();
+    for warning in warnings.iter() {
+        let diagnostic = Diagnostic {
+            severity: options.warning_severity(),
+            ..Diagnostic::from(warning)
+        };
+        eprintln!("{}", diagnostic.render(options.error_format, &file));
+    }
+
+    compiler::generate(&program)?;
+    if options.warnings_as_errors && !warnings.is_empty() {
+        let count = warnings.length();
+        return Err(CompileError::Warnings { count }.into());
+    }
     Ok(())
 }
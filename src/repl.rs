@@ -0,0 +1,335 @@
+//! The interactive read-eval-print loop.
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Instant;
+
+use rustyline::DefaultEditor;
+use rustyline::error::ReadlineError;
+
+use crate::compiler;
+use crate::vm::Vm;
+use crate::vm::disassembler::Disassembler;
+use crate::vm::error::RoxError;
+use crate::vm::value::Value;
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = "... ";
+
+const HELP: &str = "\
+:dis <expr>     Disassemble <expr> without running it
+:globals        List defined globals and their values
+:stack          Show the VM's value stack
+:load <file>    Run a file in the current session
+:reset          Reset the VM to a fresh state
+:time <expr>    Run <expr> and report how long it took
+:gc             Summarize the VM's memory use
+:help           Show this help
+:quit           Leave the REPL";
+
+#[derive(Debug, PartialEq, Eq)]
+enum Flow {
+    Continue,
+    Quit,
+}
+
+pub fn run(vm: &mut Vm) -> Result<(), RoxError> {
+    let mut editor = DefaultEditor::new().map_err(into_io)?;
+    let history = history_path();
+    if let Some(path) = &history {
+        // A missing history file just means this is the first session.
+        let _ = editor.load_history(path);
+    }
+
+    // The editor reads Ctrl-C as a key, so the signal only arrives while an
+    // entry is running. It then stops the entry instead of the process.
+    let interrupt = vm.interrupt_handle();
+    if let Err(e) = ctrlc::set_handler(move || interrupt.interrupt()) {
+        eprintln!("Ctrl-C will not interrupt running code: {e}");
+    }
+
+    while let Some(source) = read_entry(&mut editor)? {
+        if source.trim().is_empty() {
+            continue;
+        }
+
+        let _ = editor.add_history_entry(source.trim_end());
+        if let Some(path) = &history {
+            save_history(&mut editor, path);
+        }
+
+        if evaluate(vm, &source, &mut io::stdout(), &mut io::stderr()) == Flow::Quit {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Evaluates one entry. Errors are written to `err` and end the entry, not
+/// the session: the VM resets its own stack after a runtime error so the
+/// next entry starts clean.
+///
+/// A Ctrl-C that arrived after the previous entry finished is dropped, so
+/// it cannot stop this one.
+fn evaluate<W: Write>(
+    vm: &mut Vm<W>,
+    source: &str,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> Flow {
+    vm.interrupt_handle().clear();
+    let result = match source.trim().strip_prefix(':') {
+        Some(command) => meta_command(vm, command, out),
+        None => vm.interpret(source).map(|_| Flow::Continue),
+    };
+    result.unwrap_or_else(|e| {
+        let _ = writeln!(err, "{e}");
+        Flow::Continue
+    })
+}
+
+/// Reads lines until they form a complete entry, showing a continuation
+/// prompt while brackets or a string are still open. Returns `None` on EOF.
+fn read_entry(editor: &mut DefaultEditor) -> Result<Option<String>, RoxError> {
+    read_entry_with(|prompt| editor.readline(prompt))
+}
+
+/// [`read_entry`] over any source of lines, such as a test script.
+fn read_entry_with(
+    mut readline: impl FnMut(&str) -> Result<String, ReadlineError>,
+) -> Result<Option<String>, RoxError> {
+    let mut source = String::new();
+    loop {
+        let prompt = if source.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+
+        match readline(prompt) {
+            Ok(line) => {
+                source.push_str(&line);
+                source.push('\n');
+                // Meta-commands always fit on one line.
+                if source.starts_with(':') || !compiler::is_incomplete(&source) {
+                    return Ok(Some(source));
+                }
+            }
+            // Ctrl-C abandons the current entry but keeps the session going.
+            Err(ReadlineError::Interrupted) => source.clear(),
+            Err(ReadlineError::Eof) => return Ok(None),
+            Err(e) => return Err(into_io(e).into()),
+        }
+    }
+}
+
+/// Runs a colon-prefixed REPL command (without the colon).
+fn meta_command<W: Write>(
+    vm: &mut Vm<W>,
+    command: &str,
+    out: &mut impl Write,
+) -> Result<Flow, RoxError> {
+    let (name, argument) = command
+        .split_once(char::is_whitespace)
+        .map_or((command, ""), |(name, argument)| (name, argument.trim()));
+
+    match name {
+        "dis" => {
+            let chunk = compiler::compile_with(argument, vm.compile_options())?;
+            Disassembler::new(&chunk, argument).write(out)?;
+        }
+        "globals" => match vm.globals() {
+            [] => writeln!(out, "(no globals)")?,
+            globals => {
+                for (name, value) in globals {
+                    writeln!(out, "{name} = {value}")?;
+                }
+            }
+        },
+        "stack" => writeln!(out, "{}", vm.stack())?,
+        "load" => {
+            let source = std::fs::read_to_string(argument)?;
+            vm.interpret(&source)?;
+        }
+        "reset" => vm.reset(),
+        "time" => {
+            let start = Instant::now();
+            let result = vm.interpret(argument);
+            writeln!(out, "took {:?}", start.elapsed())?;
+            result?;
+        }
+        // Values are all numbers so far, so the stack is the only memory.
+        "gc" => {
+            let capacity = vm.stack_capacity();
+            writeln!(
+                out,
+                "stack: {} values, capacity {capacity} ({} bytes); no heap objects yet",
+                vm.stack_values().len(),
+                capacity * size_of::<Value>(),
+            )?;
+        }
+        "help" => writeln!(out, "{HELP}")?,
+        "quit" | "q" => return Ok(Flow::Quit),
+        _ => writeln!(out, "Unknown command ':{name}'. Try :help.")?,
+    }
+    Ok(Flow::Continue)
+}
+
+fn save_history(editor: &mut DefaultEditor, path: &PathBuf) {
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let _ = editor.save_history(path);
+}
+
+/// `$XDG_DATA_HOME/rox/history`, falling back to `~/.local/share/rox/history`.
+fn history_path() -> Option<PathBuf> {
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(data_dir.join("rox").join("history"))
+}
+
+fn into_io(err: ReadlineError) -> io::Error {
+    match err {
+        ReadlineError::Io(e) => e,
+        other => io::Error::other(other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use super::*;
+    use crate::array::Array;
+    use crate::compiler::CompileOptions;
+    use crate::vm::error::CompileError;
+
+    fn meta(vm: &mut Vm<Array<u8>>, command: &str) -> (Flow, String) {
+        let mut out = Array::default();
+        let flow = meta_command(vm, command, &mut out).unwrap();
+        (flow, std::str::from_utf8(&out).unwrap().to_owned())
+    }
+
+    #[test]
+    fn dis_does_not_run() {
+        let mut vm = Vm::with_output(Array::default());
+        let (flow, out) = meta(&mut vm, "dis 1 + 2");
+        assert_eq!(flow, Flow::Continue);
+        assert!(out.starts_with("== 1 + 2 =="));
+        assert!(out.contains("OP_ADD"));
+        assert!(vm.into_output().is_empty());
+    }
+
+    #[test]
+    fn stack_and_time() {
+        let mut vm = Vm::with_output(Array::default());
+        assert_eq!(meta(&mut vm, "stack").1, "[ ]\n");
+        assert!(meta(&mut vm, "time 2 * 3").1.starts_with("took "));
+        assert_eq!(&*vm.into_output(), b"6\n");
+    }
+
+    #[test]
+    fn quit_and_unknown() {
+        let mut vm = Vm::with_output(Array::default());
+        assert_eq!(meta(&mut vm, "quit").0, Flow::Quit);
+        assert!(meta(&mut vm, "nope").1.contains("Unknown command ':nope'"));
+    }
+
+    #[test]
+    fn globals_and_gc() {
+        let mut vm = Vm::with_output(Array::default());
+        assert_eq!(meta(&mut vm, "globals").1, "(no globals)\n");
+        assert!(meta(&mut vm, "gc").1.starts_with("stack: 0 values, "));
+
+        vm.interpret("1 + 2").unwrap();
+        let capacity = vm.stack_capacity();
+        assert!(capacity >= 2);
+        assert_eq!(
+            meta(&mut vm, "gc").1,
+            format!(
+                "stack: 0 values, capacity {capacity} ({} bytes); no heap objects yet\n",
+                capacity * size_of::<Value>()
+            )
+        );
+    }
+
+    fn read(lines: &[&str]) -> (Option<String>, Vec<String>) {
+        let mut lines = lines.iter();
+        let mut prompts = Vec::new();
+        let entry = read_entry_with(|prompt| {
+            prompts.push(prompt.to_owned());
+            match lines.next() {
+                Some(&"^C") => Err(ReadlineError::Interrupted),
+                Some(line) => Ok(line.to_string()),
+                None => Err(ReadlineError::Eof),
+            }
+        })
+        .unwrap();
+        (entry, prompts)
+    }
+
+    #[test]
+    fn entries_span_lines_until_complete() {
+        let (entry, prompts) = read(&["(1 +", "2)"]);
+        assert_eq!(entry.as_deref(), Some("(1 +\n2)\n"));
+        assert_eq!(prompts, [PROMPT, CONTINUATION_PROMPT]);
+
+        assert_eq!(read(&[":dis (1 +"]).0.as_deref(), Some(":dis (1 +\n"));
+        assert_eq!(read(&["(1 +", "^C", "3"]).0.as_deref(), Some("3\n"));
+        assert_eq!(read(&["(1 +"]).0, None);
+    }
+
+    #[test]
+    fn errors_end_the_entry_not_the_session() {
+        let mut vm = Vm::with_output(Array::default());
+        vm.set_max_stack_size(2);
+        let mut out = Array::default();
+        let mut err = Array::default();
+        for entry in [
+            "(1 +",
+            "1 + (2 + 3)",
+            ":load /nonexistent/file.lox",
+            "2 * 3",
+        ] {
+            assert_eq!(evaluate(&mut vm, entry, &mut out, &mut err), Flow::Continue);
+        }
+        let err = String::from_utf8(err.to_vec()).unwrap();
+        let errors: Vec<&str> = err.lines().filter(|line| !line.starts_with('[')).collect();
+        assert_eq!(errors.len(), 3, "{err}");
+        assert!(errors[1].starts_with("Stack overflow"));
+        assert_eq!(meta(&mut vm, "stack").1, "[ ]\n");
+        assert_eq!(&*vm.into_output(), b"6\n");
+    }
+
+    #[test]
+    fn stale_interrupt_does_not_stop_the_next_entry() {
+        let mut vm = Vm::with_output(Array::default());
+        vm.interrupt_handle().interrupt();
+        let mut err = Array::default();
+        evaluate(&mut vm, "1 + 2", &mut io::sink(), &mut err);
+        assert!(err.is_empty());
+        assert_eq!(&*vm.into_output(), b"3\n");
+    }
+
+    #[test]
+    fn errors_are_returned() {
+        let mut vm = Vm::with_output(Array::default());
+        let mut out = Array::default();
+        assert!(meta_command(&mut vm, "dis (1 +", &mut out).is_err());
+        assert!(meta_command(&mut vm, "load /nonexistent/file.lox", &mut out).is_err());
+
+        // `:dis` compiles with the session's options.
+        vm.set_compile_options(CompileOptions {
+            max_errors: NonZeroUsize::new(1),
+            ..CompileOptions::default()
+        });
+        match meta_command(&mut vm, "dis 1 +; 2 *; 3 /", &mut out) {
+            Err(RoxError::Compile(CompileError::Parse(errors))) => assert_eq!(errors.0.length(), 1),
+            other => panic!("expected parse errors, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! `rox test`: runs Lox files and checks their output against expectations
+//! written in comments, following the conventions of the book's test suite.
+//!
+//! ```lox
+//! 1 + 2 // expect: 3
+//! ```
+//!
+//! A `// expect runtime error: <message>` comment expects the script to fail
+//! at runtime with that message instead.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::array::Array;
+use crate::vm::Vm;
+use crate::vm::error::RoxError;
+
+const EXPECT: &str = "// expect: ";
+const EXPECT_RUNTIME_ERROR: &str = "// expect runtime error: ";
+
+struct Expectations {
+    output: Array<String>,
+    runtime_error: Option<String>,
+}
+
+impl Expectations {
+    fn parse(source: &str) -> Self {
+        let mut expectations = Self {
+            output: Array::default(),
+            runtime_error: None,
+        };
+        for line in source.lines() {
+            if let Some((_, expected)) = line.split_once(EXPECT) {
+                expectations.output.push(expected.to_owned());
+            } else if let Some((_, message)) = line.split_once(EXPECT_RUNTIME_ERROR) {
+                expectations.runtime_error = Some(message.to_owned());
+            }
+        }
+        expectations
+    }
+}
+
+/// Runs every `.lox` file under `paths`, writing a line per test to `w`.
+/// Returns whether all of them passed.
+pub fn run<W: Write>(paths: &[String], w: &mut W) -> io::Result<bool> {
+    let mut files = Array::default();
+    for path in paths {
+        collect_files(Path::new(path), &mut files)?;
+    }
+
+    let mut failures = 0;
+    for file in files.iter() {
+        let source = std::fs::read_to_string(file)?;
+        match check(&source) {
+            Ok(()) => writeln!(w, "PASS {}", file.display())?,
+            Err(reason) => {
+                failures += 1;
+                writeln!(w, "FAIL {}\n     {reason}", file.display())?;
+            }
+        }
+    }
+
+    writeln!(
+        w,
+        "\n{} passed, {failures} failed",
+        files.length() - failures
+    )?;
+    Ok(failures == 0)
+}
+
+fn collect_files(path: &Path, files: &mut Array<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_owned());
+        return Ok(());
+    }
+
+    let mut entries: Array<PathBuf> = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    entries.sort();
+    for entry in entries.iter() {
+        if entry.is_dir() || entry.extension().is_some_and(|ext| ext == "lox") {
+            collect_files(entry, files)?;
+        }
+    }
+    Ok(())
+}
+
+fn check(source: &str) -> Result<(), String> {
+    let expectations = Expectations::parse(source);
+
+    let mut vm = Vm::with_output(Array::default());
+    let result = vm.interpret(source);
+    let output = vm.into_output();
+    let output = String::from_utf8_lossy(&output);
+
+    match (result, &expectations.runtime_error) {
+        (Ok(_), Some(message)) => {
+            return Err(format!(
+                "expected runtime error '{message}' but the script succeeded"
+            ));
+        }
+        (Err(RoxError::Runtime(e)), Some(message)) if !e.to_string().contains(message.as_str()) => {
+            return Err(format!("expected runtime error '{message}', got '{e}'"));
+        }
+        (Err(RoxError::Runtime(_)), Some(_)) | (Ok(_), None) => {}
+        (Err(e), _) => return Err(format!("unexpected error: {e}")),
+    }
+
+    let actual: Array<&str> = output.lines().collect();
+    if actual.length() != expectations.output.length() {
+        return Err(format!(
+            "expected {} line(s) of output, got {}",
+            expectations.output.length(),
+            actual.length()
+        ));
+    }
+    for (i, (actual, expected)) in actual.iter().zip(expectations.output.iter()).enumerate() {
+        if actual != expected {
+            return Err(format!(
+                "output line {}: expected '{expected}', got '{actual}'",
+                i + 1
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passes_on_matching_output() {
+        assert_eq!(check("(1 + 2) * 3 // expect: 9"), Ok(()));
+    }
+
+    #[test]
+    fn fails_on_mismatched_output() {
+        assert_eq!(
+            check("1 + 2 // expect: 4"),
+            Err("output line 1: expected '4', got '3'".to_owned())
+        );
+    }
+
+    #[test]
+    fn fails_on_missing_output_and_errors() {
+        assert!(check("1 // expect: 1\n// expect: 2").is_err());
+        assert!(check("(1 +").is_err());
+    }
+}
@@ -0,0 +1,99 @@
+//! The `Content-Length` framing shared by the Debug Adapter and Language
+//! Server protocols.
+
+use std::io::{self, BufRead, Write};
+
+use crate::json::Json;
+
+/// The largest message body accepted. The length comes from the client, so
+/// it is checked before anything is allocated.
+const MAX_MESSAGE_BYTES: usize = 8 << 20;
+
+/// Reads one message. Returns `None` when the input ends between messages.
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Json>> {
+    let mut length = None;
+    let mut header = String::new();
+    loop {
+        header.clear();
+        if reader.read_line(&mut header)? == 0 {
+            return match length {
+                None => Ok(None),
+                Some(_) => Err(invalid("input ended inside a message header")),
+            };
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        // Other headers, such as Content-Type, are allowed and ignored.
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("Content-Length")
+        {
+            let value = value
+                .trim()
+                .parse()
+                .map_err(|_| invalid("bad Content-Length"))?;
+            length = Some(value);
+        }
+    }
+
+    let length = length.ok_or_else(|| invalid("missing Content-Length"))?;
+    if length > MAX_MESSAGE_BYTES {
+        return Err(invalid("Content-Length too large"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    let body = String::from_utf8(body).map_err(|_| invalid("message is not UTF-8"))?;
+    Json::parse(&body)
+        .map(Some)
+        .map_err(|e| invalid(&e.to_string()))
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    writer.flush()
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::array::Array;
+
+    #[test]
+    fn round_trips_messages() {
+        let mut buffer = Array::default();
+        write_message(&mut buffer, &Json::object([("seq", 1usize.into())])).unwrap();
+        write_message(&mut buffer, &Json::string("é")).unwrap();
+        assert!(buffer.starts_with(b"Content-Length: 9\r\n\r\n{\"seq\":1}"));
+
+        let mut reader: &[u8] = &buffer;
+        let first = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(first.get("seq").and_then(Json::as_usize), Some(1));
+        let second = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(second.as_str(), Some("é"));
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_bad_frames() {
+        let mut missing_length = &b"Content-Type: x\r\n\r\n{}"[..];
+        assert!(read_message(&mut missing_length).is_err());
+        let mut truncated = &b"Content-Length: 10\r\n\r\n{}"[..];
+        assert!(read_message(&mut truncated).is_err());
+    }
+
+    #[test]
+    fn rejects_oversized_lengths() {
+        for length in [usize::MAX, MAX_MESSAGE_BYTES + 1] {
+            let frame = format!("Content-Length: {length}\r\n\r\n{{}}");
+            let err = read_message(&mut frame.as_bytes()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(err.to_string(), "Content-Length too large");
+        }
+    }
+}
@@ -1,32 +1,66 @@
 pub mod chunk;
+pub mod debugger;
 pub mod disassembler;
 pub mod error;
+pub mod interrupt;
+pub mod limits;
 pub mod opcode;
+pub mod serialize;
 mod stack;
 pub mod value;
+pub mod verifier;
 
 use std::io::{self, Stdout, Write};
 use std::ops::{Add, Div, Mul, Neg, Sub};
 
 use chunk::Chunk;
-use error::{CompileError, RoxError};
+use debugger::{Debugger, Pause};
+use disassembler::Disassembler;
+use error::{CompileError, RoxError, RuntimeError};
+use interrupt::InterruptHandle;
+use limits::{Budget, Halt, Limits};
 use opcode::OpCode;
-use stack::ValueStack;
+use stack::{StackError, ValueStack};
 use value::Value;
 
-use crate::compiler;
+use crate::compiler::{self, CompileOptions};
+
+/// How a call to [`Vm::run`] or [`Vm::resume`] ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunState {
+    /// The script returned.
+    Finished,
+    /// The script stopped early and continues with [`Vm::resume`].
+    Suspended,
+    /// The installed [`Debugger`] stopped the script. It continues with
+    /// [`Vm::resume`].
+    Paused(Pause),
+}
+
+/// A script paused between two instructions.
+struct Suspension {
+    chunk: Chunk,
+    offset: usize,
+    /// What is left of the limits, which cover the whole run.
+    budget: Budget,
+}
 
 pub struct Vm<W: Write = Stdout> {
     stack: ValueStack,
     output: W,
+    print_code: bool,
+    trace_execution: bool,
+    limits: Limits,
+    compile_options: CompileOptions,
+    interrupt: InterruptHandle,
+    step_budget: Option<u64>,
+    suspended: Option<Suspension>,
+    debugger: Option<Debugger>,
 }
 
 impl Default for Vm<Stdout> {
     fn default() -> Self {
-        Self {
-            stack: ValueStack::default(),
-            output: io::stdout(),
-        }
+        Self::new(io::stdout())
     }
 }
 
@@ -34,40 +68,262 @@ impl<W: Write> std::fmt::Debug for Vm<W> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Vm")
             .field("stack", &self.stack)
+            .field("print_code", &self.print_code)
+            .field("trace_execution", &self.trace_execution)
+            .field("limits", &self.limits)
+            .field("compile_options", &self.compile_options)
+            .field("interrupt", &self.interrupt)
+            .field("step_budget", &self.step_budget)
+            .field("suspended", &self.suspended.is_some())
+            .field("debugger", &self.debugger)
             .finish_non_exhaustive()
     }
 }
 
 impl<W: Write> Vm<W> {
-    #[cfg(test)]
-    pub fn with_output(output: W) -> Self {
+    fn new(output: W) -> Self {
         Self {
             stack: ValueStack::default(),
             output,
+            print_code: false,
+            trace_execution: false,
+            limits: Limits::default(),
+            compile_options: CompileOptions::default(),
+            interrupt: InterruptHandle::default(),
+            step_budget: None,
+            suspended: None,
+            debugger: None,
         }
     }
 
-    #[cfg(test)]
+    pub fn with_output(output: W) -> Self {
+        Self::new(output)
+    }
+
+    /// Limits how many values the stack may hold. It grows on demand up to
+    /// this size and exceeding it is a runtime error.
+    pub fn set_max_stack_size(&mut self, max_size: usize) {
+        self.stack.set_max_size(max_size);
+    }
+
+    /// Bounds the instructions, memory and time each run may use. Exceeding
+    /// a limit stops the run with a runtime error; the VM stays usable.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+        self.stack.set_max_bytes(limits.max_memory);
+    }
+
+    /// Options for compiling the sources passed to [`Vm::interpret`].
+    pub fn set_compile_options(&mut self, options: CompileOptions) {
+        self.compile_options = options;
+    }
+
+    /// The options [`Vm::interpret`] compiles with.
+    pub fn compile_options(&self) -> &CompileOptions {
+        &self.compile_options
+    }
+
+    /// A handle other threads can use to stop the run in progress.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    /// Suspends the script after `steps` instructions in each call to
+    /// [`Vm::run`] or [`Vm::resume`], so a host can run it a slice at a time.
+    /// The other limits cover all the slices together; time spent suspended
+    /// does not count.
+    pub fn set_step_budget(&mut self, steps: Option<u64>) {
+        self.step_budget = steps;
+    }
+
+    /// Installs a debugger that can pause the script, or removes it.
+    pub fn set_debugger(&mut self, debugger: Option<Debugger>) {
+        self.debugger = debugger;
+    }
+
+    pub fn debugger_mut(&mut self) -> Option<&mut Debugger> {
+        self.debugger.as_mut()
+    }
+
+    /// The source line the suspended script stopped at.
+    pub fn current_line(&self) -> Option<usize> {
+        self.suspended
+            .as_ref()
+            .map(|suspension| suspension.chunk.lines[suspension.offset])
+    }
+
+    /// Disassemble every chunk before running it, like clox's `DEBUG_PRINT_CODE`.
+    pub fn set_print_code(&mut self, enabled: bool) {
+        self.print_code = enabled;
+    }
+
+    /// Print the stack and each instruction before it executes, like clox's
+    /// `DEBUG_TRACE_EXECUTION`.
+    pub fn set_trace_execution(&mut self, enabled: bool) {
+        self.trace_execution = enabled;
+    }
+
+    /// The current contents of the value stack, for display.
+    pub fn stack(&self) -> impl std::fmt::Display + '_ {
+        &self.stack
+    }
+
+    /// The values on the stack, from the bottom to the top.
+    pub fn stack_values(&self) -> &[Value] {
+        self.stack.values()
+    }
+
+    /// The global variables and their values. Lox has no variables yet, so
+    /// there are none.
+    pub fn globals(&self) -> &[(String, Value)] {
+        &[]
+    }
+
+    /// The local variables of the current frame and their values, which are
+    /// always empty for the same reason as [`Vm::globals`].
+    pub fn locals(&self) -> &[(String, Value)] {
+        &[]
+    }
+
+    /// How many values the stack has room for before it must grow.
+    pub fn stack_capacity(&self) -> usize {
+        self.stack.capacity()
+    }
+
+    /// Returns the VM to a fresh state, keeping its output and settings.
+    pub fn reset(&mut self) {
+        self.stack.reset();
+        self.suspended = None;
+    }
+
+    pub fn output_mut(&mut self) -> &mut W {
+        &mut self.output
+    }
+
     pub fn into_output(self) -> W {
         self.output
     }
 
-    pub fn interpret(&mut self, source: &str) -> Result<(), RoxError> {
-        let chunk = compiler::compile(source)?;
-        self.run(&chunk)
+    pub fn interpret(&mut self, source: &str) -> Result<RunState, RoxError> {
+        let chunk = compiler::compile_with(source, &self.compile_options)?;
+        self.run(chunk)
+    }
+
+    /// Runs `chunk` from the start, abandoning any suspended script.
+    pub fn run(&mut self, chunk: Chunk) -> Result<RunState, RoxError> {
+        if self.suspended.is_some() {
+            self.reset();
+        }
+        // Verify once so the dispatch loop can read through a raw
+        // instruction pointer without per-instruction bounds or opcode checks.
+        chunk.verify().map_err(CompileError::from)?;
+        if self.print_code {
+            let _ = Disassembler::new(&chunk, "code").write(&mut self.output);
+        }
+        self.stack.prepare(chunk.max_stack_depth);
+        if let Some(debugger) = &mut self.debugger {
+            debugger.restart();
+        }
+        let budget = Budget::new(&self.limits);
+        self.continue_at(chunk, 0, budget)
+    }
+
+    /// Continues a suspended script where it stopped.
+    pub fn resume(&mut self) -> Result<RunState, RoxError> {
+        let Suspension {
+            chunk,
+            offset,
+            budget,
+        } = self.suspended.take().ok_or(RuntimeError::NotSuspended)?;
+        self.continue_at(chunk, offset, budget)
+    }
+
+    fn continue_at(
+        &mut self,
+        chunk: Chunk,
+        offset: usize,
+        mut budget: Budget,
+    ) -> Result<RunState, RoxError> {
+        budget.start();
+        let exit = self.execute(&chunk, offset, &mut budget);
+        budget.stop();
+        match exit {
+            Ok(Exit::Returned) => Ok(RunState::Finished),
+            Ok(Exit::Suspended(offset)) => {
+                self.suspended = Some(Suspension {
+                    chunk,
+                    offset,
+                    budget,
+                });
+                Ok(RunState::Suspended)
+            }
+            Ok(Exit::Paused(offset, pause)) => {
+                self.suspended = Some(Suspension {
+                    chunk,
+                    offset,
+                    budget,
+                });
+                Ok(RunState::Paused(pause))
+            }
+            Err(e) => {
+                // Leave the VM ready for the next program, e.g. in the REPL.
+                self.stack.reset();
+                Err(e)
+            }
+        }
     }
 
-    fn run(&mut self, chunk: &Chunk) -> Result<(), RoxError> {
-        let mut instruction_pointer = 0usize;
+    /// Executes a verified `chunk` from the instruction at `start`.
+    fn execute(
+        &mut self,
+        chunk: &Chunk,
+        start: usize,
+        budget: &mut Budget,
+    ) -> Result<Exit, RoxError> {
+        let mut steps = self.step_budget;
+        let code_start = chunk.codes.as_ptr();
+        // SAFETY: `start` is 0 or the offset of an instruction we stopped before.
+        let mut instruction_pointer = unsafe { code_start.add(start) };
 
         loop {
-            let code_u8 = chunk.codes[instruction_pointer];
+            // SAFETY: both pointers are into the same code allocation.
+            let offset = unsafe { instruction_pointer.offset_from(code_start) } as usize;
+            if let Some(steps) = &mut steps {
+                if *steps == 0 {
+                    break Ok(Exit::Suspended(offset));
+                }
+                *steps -= 1;
+            }
+            if let Some(debugger) = &mut self.debugger {
+                let line = chunk.lines[offset];
+                // Only the script's frame exists until calls land.
+                if let Some(reason) = debugger.check(line, 1) {
+                    break Ok(Exit::Paused(offset, Pause { line, reason }));
+                }
+            }
+            budget
+                .tick(&self.interrupt)
+                .map_err(|e| halt_error(e, chunk.lines[offset]))?;
+            if self.trace_execution {
+                self.trace_instruction(chunk, offset);
+            }
+
+            // SAFETY: `verify` guarantees that every instruction is complete and
+            // that `OP_RETURN` is reached before the end of the code.
+            let code_u8 = unsafe { read_byte(&mut instruction_pointer) };
+
+            // Debug builds keep the checked decode as a safety net for the verifier.
+            #[cfg(debug_assertions)]
             let opcode = OpCode::try_from(code_u8).map_err(CompileError::UnknownOpcode)?;
+            // SAFETY: `verify` rejected every byte in opcode position that is not an `OpCode`.
+            #[cfg(not(debug_assertions))]
+            let opcode = unsafe { OpCode::from_u8_unchecked(code_u8) };
 
-            match opcode {
+            let result = match opcode {
                 OpCode::Return => {
-                    self.interpret_return();
-                    break Ok(());
+                    self.interpret_return()
+                        .map_err(|e| runtime_error(e, chunk, offset))?;
+                    break Ok(Exit::Returned);
                 }
                 OpCode::Negate => self.interpret_negate(),
                 OpCode::Constant => self.interpret_constant(&mut instruction_pointer, chunk),
@@ -75,33 +331,361 @@ impl<W: Write> Vm<W> {
                 OpCode::Subtract => self.interpret_binary_op(Value::sub),
                 OpCode::Multiply => self.interpret_binary_op(Value::mul),
                 OpCode::Divide => self.interpret_binary_op(Value::div),
-            }
-            instruction_pointer += 1;
+            };
+            result.map_err(|e| runtime_error(e, chunk, offset))?;
         }
     }
 
-    fn interpret_return(&mut self) {
-        let value = self.stack.pop();
+    fn trace_instruction(&mut self, chunk: &Chunk, offset: usize) {
+        let _ = writeln!(self.output, "          {}", self.stack);
+        let _ = Disassembler::new(chunk, "trace").disassemble_instruction(&mut self.output, offset);
+    }
+
+    fn interpret_return(&mut self) -> Result<(), StackError> {
+        let value = self.stack.pop()?;
         let _ = writeln!(self.output, "{value}");
+        Ok(())
     }
 
-    fn interpret_negate(&mut self) {
-        let value = self.stack.pop();
+    fn interpret_negate(&mut self) -> Result<(), StackError> {
+        let value = self.stack.pop()?;
         let negated = Value::from(f64::from(value).neg());
-        self.stack.push(negated);
+        self.stack.push(negated)
+    }
+
+    fn interpret_constant(
+        &mut self,
+        instruction_pointer: &mut *const u8,
+        chunk: &Chunk,
+    ) -> Result<(), StackError> {
+        // SAFETY: `verify` checked that the operand is present and indexes the constant pool.
+        let constant_value = unsafe {
+            let constant_index = read_byte(instruction_pointer) as usize;
+            *chunk.constants.get_unchecked(constant_index)
+        };
+        self.stack.push(constant_value)
+    }
+
+    fn interpret_binary_op(
+        &mut self,
+        op: impl Fn(Value, Value) -> Value,
+    ) -> Result<(), StackError> {
+        let v2 = self.stack.pop()?;
+        let v1 = self.stack.pop()?;
+        self.stack.push(op(v1, v2))
+    }
+}
+
+/// Why the dispatch loop stopped without an error.
+enum Exit {
+    Returned,
+    /// Stopped before the instruction at this offset.
+    Suspended(usize),
+    Paused(usize, Pause),
+}
+
+/// Attaches the source line of the instruction at `offset` to a stack error.
+fn runtime_error(error: StackError, chunk: &Chunk, offset: usize) -> RuntimeError {
+    let line = chunk.lines[offset];
+    match error {
+        StackError::Overflow { max } => RuntimeError::StackOverflow { max, line },
+        StackError::Underflow => RuntimeError::StackUnderflow { line },
+        StackError::OutOfMemory { limit } => RuntimeError::OutOfMemory { limit, line },
+    }
+}
+
+fn halt_error(halt: Halt, line: usize) -> RuntimeError {
+    match halt {
+        Halt::Fuel => RuntimeError::FuelExhausted { line },
+        Halt::Timeout => RuntimeError::Timeout { line },
+        Halt::Interrupted => RuntimeError::Interrupted { line },
+    }
+}
+
+/// Reads the byte under the instruction pointer and advances it.
+///
+/// # Safety
+///
+/// `instruction_pointer` must point inside a verified chunk's code.
+#[inline(always)]
+unsafe fn read_byte(instruction_pointer: &mut *const u8) -> u8 {
+    unsafe {
+        let byte = **instruction_pointer;
+        *instruction_pointer = instruction_pointer.add(1);
+        byte
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::array::Array;
+    use crate::vm::verifier::VerifyError;
+
+    fn run_chunk(chunk: Chunk) -> Result<Array<u8>, RoxError> {
+        let mut vm = Vm::with_output(Array::default());
+        vm.run(chunk)?;
+        Ok(vm.into_output())
+    }
+
+    #[test]
+    fn runs_handwritten_chunk() {
+        let mut chunk = Chunk::default();
+        let idx = chunk.write_constant(1.5.into()).unwrap();
+        chunk.write_opcode(OpCode::Constant, 1);
+        chunk.write_byte(idx, 1);
+        chunk.write_opcode(OpCode::Negate, 1);
+        chunk.write_opcode(OpCode::Return, 1);
+
+        assert_eq!(&*run_chunk(chunk).unwrap(), b"-1.5\n");
+    }
+
+    #[test]
+    fn rejects_malformed_chunk() {
+        let mut chunk = Chunk::default();
+        chunk.write_byte(0xff, 1);
+        chunk.write_opcode(OpCode::Return, 1);
+
+        let err = run_chunk(chunk).unwrap_err();
+        assert!(matches!(
+            err,
+            RoxError::Compile(CompileError::Verify(VerifyError::UnknownOpcode {
+                offset: 0,
+                byte: 0xff
+            }))
+        ));
+    }
+
+    #[test]
+    fn print_code_disassembles_before_running() {
+        let mut vm = Vm::with_output(Array::default());
+        vm.set_print_code(true);
+        vm.interpret("-1").unwrap();
+
+        let output = String::from_utf8(vm.into_output().to_vec()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "== code ==",
+                "0000    1 OP_CONSTANT         0 '1'",
+                "0002    | OP_NEGATE",
+                "0003    | OP_RETURN",
+                "max stack depth: 1",
+                "-1",
+            ]
+        );
+    }
+
+    #[test]
+    fn trace_execution_prints_stack_before_each_instruction() {
+        let mut vm = Vm::with_output(Array::default());
+        vm.set_trace_execution(true);
+        vm.interpret("1 + 2").unwrap();
+
+        let output = String::from_utf8(vm.into_output().to_vec()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "          [ ]",
+                "0000    1 OP_CONSTANT         0 '1'",
+                "          [ 1 ]",
+                "0002    | OP_CONSTANT         1 '2'",
+                "          [ 1 2 ]",
+                "0004    | OP_ADD",
+                "          [ 3 ]",
+                "0005    | OP_RETURN",
+                "3",
+            ]
+        );
+    }
+
+    #[test]
+    fn vm_is_reusable_after_errors() {
+        let mut vm = Vm::with_output(Array::default());
+        assert!(vm.interpret("(1 +").is_err());
+        assert!(vm.run(Chunk::default()).is_err());
+        vm.interpret("1 + 1").unwrap();
+        assert_eq!(&*vm.into_output(), b"2\n");
+    }
+
+    #[test]
+    fn stack_overflow_is_a_runtime_error() {
+        let mut vm = Vm::with_output(Array::default());
+        vm.set_max_stack_size(2);
+        let err = vm.interpret("1 +\n(2 + 3)").unwrap_err();
+        assert!(matches!(
+            err,
+            RoxError::Runtime(RuntimeError::StackOverflow { max: 2, line: 2 })
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Stack overflow (maximum stack size is 2). [E0100]\n[line 2] in script"
+        );
+
+        vm.set_max_stack_size(3);
+        vm.interpret("1 +\n(2 + 3)").unwrap();
+        assert_eq!(&*vm.into_output(), b"6\n");
+    }
+
+    #[test]
+    fn deep_expressions_grow_the_stack() {
+        let source = format!("{}1{}", "(1 + ".repeat(200), ")".repeat(200));
+        let mut vm = Vm::with_output(Array::default());
+        vm.set_max_stack_size(100);
+        assert!(vm.interpret(&source).is_err());
+
+        vm.set_max_stack_size(1000);
+        vm.interpret(&source).unwrap();
+        assert_eq!(&*vm.into_output(), b"201\n");
+    }
+
+    #[test]
+    fn fuel_limit_stops_the_run() {
+        let mut vm = Vm::with_output(Array::default());
+        vm.set_limits(Limits {
+            fuel: Some(3),
+            ..Limits::default()
+        });
+        // Four instructions: two constants, an add and the return.
+        let err = vm.interpret("1 +\n2").unwrap_err();
+        assert!(matches!(
+            err,
+            RoxError::Runtime(RuntimeError::FuelExhausted { line: 2 })
+        ));
+
+        vm.set_limits(Limits {
+            fuel: Some(4),
+            ..Limits::default()
+        });
+        vm.interpret("1 +\n2").unwrap();
+        assert_eq!(&*vm.into_output(), b"3\n");
+    }
+
+    #[test]
+    fn timeout_stops_the_run() {
+        let mut vm = Vm::with_output(Array::default());
+        vm.set_limits(Limits {
+            timeout: Some(std::time::Duration::ZERO),
+            ..Limits::default()
+        });
+        let err = vm.interpret("1").unwrap_err();
+        assert!(matches!(
+            err,
+            RoxError::Runtime(RuntimeError::Timeout { line: 1 })
+        ));
+    }
+
+    #[test]
+    fn memory_limit_stops_the_run() {
+        let mut vm = Vm::with_output(Array::default());
+        vm.set_limits(Limits {
+            max_memory: Some(2 * size_of::<Value>()),
+            ..Limits::default()
+        });
+        let err = vm.interpret("1 + (2 + 3)").unwrap_err();
+        assert!(matches!(
+            err,
+            RoxError::Runtime(RuntimeError::OutOfMemory { line: 1, .. })
+        ));
+
+        // The failed run released its stack, so smaller programs still fit.
+        vm.interpret("1 + 2").unwrap();
+        assert_eq!(&*vm.into_output(), b"3\n");
+    }
+
+    #[test]
+    fn interrupt_stops_the_run() {
+        let mut vm = Vm::with_output(Array::default());
+        vm.interrupt_handle().interrupt();
+        let err = vm.interpret("\n1").unwrap_err();
+        assert!(matches!(
+            err,
+            RoxError::Runtime(RuntimeError::Interrupted { line: 2 })
+        ));
+
+        // The interrupt is spent, so the next run completes.
+        vm.interpret("1").unwrap();
+        assert_eq!(&*vm.into_output(), b"1\n");
+    }
+
+    #[test]
+    fn step_budget_suspends_and_resumes() {
+        let mut vm = Vm::with_output(Array::default());
+        vm.set_step_budget(Some(2));
+        assert_eq!(vm.interpret("1 + 2 * 3").unwrap(), RunState::Suspended);
+        assert_eq!(vm.stack().to_string(), "[ 1 2 ]");
+
+        assert_eq!(vm.resume().unwrap(), RunState::Suspended);
+        assert_eq!(vm.stack().to_string(), "[ 1 6 ]");
+        assert_eq!(vm.resume().unwrap(), RunState::Finished);
+        assert!(matches!(
+            vm.resume(),
+            Err(RoxError::Runtime(RuntimeError::NotSuspended))
+        ));
+        assert_eq!(&*vm.into_output(), b"7\n");
+    }
+
+    #[test]
+    fn limits_cover_every_slice() {
+        let mut vm = Vm::with_output(Array::default());
+        vm.set_limits(Limits {
+            fuel: Some(5),
+            ..Limits::default()
+        });
+        vm.set_step_budget(Some(2));
+        // Six instructions: three slices would fit if each had its own fuel.
+        assert_eq!(vm.interpret("1 + 2 * 3").unwrap(), RunState::Suspended);
+        assert_eq!(vm.resume().unwrap(), RunState::Suspended);
+        assert!(matches!(
+            vm.resume(),
+            Err(RoxError::Runtime(RuntimeError::FuelExhausted { .. }))
+        ));
     }
 
-    fn interpret_constant(&mut self, instruction_pointer: &mut usize, chunk: &Chunk) {
-        // Increment to get constant offset
-        *instruction_pointer += 1;
-        let constant_index = chunk.codes[*instruction_pointer] as usize;
-        let constant_value = chunk.constants[constant_index];
-        self.stack.push(constant_value);
+    #[test]
+    fn run_abandons_suspended_script() {
+        let mut vm = Vm::with_output(Array::default());
+        vm.set_step_budget(Some(1));
+        assert_eq!(vm.interpret("1 + 2").unwrap(), RunState::Suspended);
+
+        vm.set_step_budget(None);
+        assert_eq!(vm.interpret("3").unwrap(), RunState::Finished);
+        assert_eq!(&*vm.into_output(), b"3\n");
     }
 
-    fn interpret_binary_op(&mut self, op: impl Fn(Value, Value) -> Value) {
-        let v2 = self.stack.pop();
-        let v1 = self.stack.pop();
-        self.stack.push(op(v1, v2));
+    #[test]
+    fn debugger_pauses_at_breakpoints() {
+        let mut debugger = Debugger::default();
+        debugger.add_breakpoint(2);
+        let mut vm = Vm::with_output(Array::default());
+        vm.set_debugger(Some(debugger));
+
+        let pause = Pause {
+            line: 2,
+            reason: debugger::PauseReason::Breakpoint,
+        };
+        assert_eq!(
+            vm.interpret("1 +\n2 *\n3").unwrap(),
+            RunState::Paused(pause)
+        );
+        assert_eq!(vm.current_line(), Some(2));
+        assert_eq!(vm.stack().to_string(), "[ 1 ]");
+
+        vm.debugger_mut()
+            .unwrap()
+            .set_mode(debugger::StepMode::Step);
+        assert!(matches!(
+            vm.resume().unwrap(),
+            RunState::Paused(Pause { line: 3, .. })
+        ));
+        assert_eq!(vm.stack().to_string(), "[ 1 2 ]");
+
+        vm.debugger_mut()
+            .unwrap()
+            .set_mode(debugger::StepMode::Continue);
+        assert_eq!(vm.resume().unwrap(), RunState::Finished);
+        assert_eq!(&*vm.into_output(), b"7\n");
     }
 }
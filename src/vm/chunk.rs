@@ -12,6 +12,10 @@ pub struct Chunk {
     pub codes: Array<u8>,
     pub lines: Array<usize>,
     pub constants: Array<Value>,
+    /// The deepest the operand stack gets while running this chunk.
+    pub max_stack_depth: usize,
+    /// Stack depth after the last written opcode, used to compute `max_stack_depth`.
+    current_depth: usize,
 }
 
 #[allow(dead_code)]
@@ -22,6 +26,10 @@ pub struct ExceededConstantCount(#[from] TryFromIntError);
 #[allow(dead_code)]
 impl Chunk {
     pub fn write_opcode(&mut self, opcode: OpCode, line: usize) {
+        let (pops, pushes) = opcode.stack_effect();
+        self.current_depth = self.current_depth.saturating_sub(pops) + pushes;
+        self.max_stack_depth = self.max_stack_depth.max(self.current_depth);
+
         self.write_byte(opcode as u8, line);
     }
 
@@ -59,6 +67,23 @@ mod tests {
         assert_eq!(chunk.lines[0], 123);
     }
 
+    #[test]
+    fn test_max_stack_depth() {
+        let mut chunk = Chunk::default();
+        chunk.write_opcode(OpCode::Constant, 1);
+        chunk.write_byte(0, 1);
+        chunk.write_opcode(OpCode::Constant, 1);
+        chunk.write_byte(0, 1);
+        chunk.write_opcode(OpCode::Add, 1);
+        chunk.write_opcode(OpCode::Constant, 1);
+        chunk.write_byte(0, 1);
+        chunk.write_opcode(OpCode::Negate, 1);
+        assert_eq!(chunk.max_stack_depth, 2);
+        chunk.write_opcode(OpCode::Constant, 1);
+        chunk.write_byte(0, 1);
+        assert_eq!(chunk.max_stack_depth, 3);
+    }
+
     #[test]
     fn test_constant_limit() {
         let mut chunk = Chunk::default();
@@ -0,0 +1,199 @@
+use crate::array::Array;
+
+/// Where execution goes after a pause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepMode {
+    /// Run until a breakpoint.
+    Continue,
+    /// Stop at the next line, entering calls.
+    Step,
+    /// Stop at the next line in the same frame, running calls to completion.
+    Next,
+    /// Stop once the current frame returns.
+    Finish,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseReason {
+    Breakpoint,
+    Step,
+}
+
+/// Why and where a debugged script stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pause {
+    pub line: usize,
+    pub reason: PauseReason,
+}
+
+/// Breakpoints and stepping state, installed on a [`Vm`](super::Vm) with
+/// [`Vm::set_debugger`](super::Vm::set_debugger).
+///
+/// The VM consults it before each instruction and stops with
+/// [`RunState::Paused`](super::RunState::Paused) when it asks to, so the
+/// host can inspect the VM and then [`resume`](super::Vm::resume) it.
+#[derive(Debug)]
+pub struct Debugger {
+    breakpoints: Array<usize>,
+    mode: StepMode,
+    /// The line and frame depth of the last instruction checked.
+    previous: Option<(usize, usize)>,
+    /// Where the current step started; set on the first instruction after a pause.
+    origin: Option<(usize, usize)>,
+    resuming: bool,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self {
+            breakpoints: Array::default(),
+            mode: StepMode::Continue,
+            previous: None,
+            origin: None,
+            resuming: false,
+        }
+    }
+}
+
+impl Debugger {
+    /// Adds a breakpoint on `line`. Returns `false` if there already was one.
+    pub fn add_breakpoint(&mut self, line: usize) -> bool {
+        if self.breakpoints.contains(&line) {
+            return false;
+        }
+        self.breakpoints.push(line);
+        self.breakpoints.sort_unstable();
+        true
+    }
+
+    /// Removes the breakpoint on `line`. Returns `false` if there was none.
+    pub fn remove_breakpoint(&mut self, line: usize) -> bool {
+        let Some(index) = self.breakpoints.iter().position(|&l| l == line) else {
+            return false;
+        };
+        self.breakpoints.remove(index);
+        true
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn breakpoints(&self) -> &[usize] {
+        &self.breakpoints
+    }
+
+    /// Sets how the next [`resume`](super::Vm::resume) or run proceeds.
+    pub fn set_mode(&mut self, mode: StepMode) {
+        self.mode = mode;
+        self.origin = None;
+    }
+
+    /// Forgets the position of a previous run, keeping breakpoints and mode.
+    pub(super) fn restart(&mut self) {
+        self.previous = None;
+        self.origin = None;
+        self.resuming = false;
+    }
+
+    /// Decides whether to pause before an instruction on `line`, running in
+    /// a frame `depth` calls deep.
+    pub(super) fn check(&mut self, line: usize, depth: usize) -> Option<PauseReason> {
+        let here = (line, depth);
+        let entered_line = self.previous != Some(here);
+        self.previous = Some(here);
+
+        // The instruction we paused before runs without pausing again.
+        if std::mem::take(&mut self.resuming) {
+            self.origin = Some(here);
+            return None;
+        }
+        let origin = self.origin;
+        self.origin.get_or_insert(here);
+
+        let reason = if entered_line && self.breakpoints.contains(&line) {
+            Some(PauseReason::Breakpoint)
+        } else {
+            let stepped = match (self.mode, origin) {
+                (StepMode::Continue, _) => false,
+                // Stepping into a fresh run stops before its first instruction.
+                (StepMode::Step | StepMode::Next, None) => true,
+                (StepMode::Finish, None) => false,
+                (StepMode::Step, Some(origin)) => entered_line && here != origin,
+                (StepMode::Next, Some((origin_line, origin_depth))) => {
+                    depth < origin_depth || (depth == origin_depth && line != origin_line)
+                }
+                (StepMode::Finish, Some((_, origin_depth))) => depth < origin_depth,
+            };
+            stepped.then_some(PauseReason::Step)
+        };
+
+        if reason.is_some() {
+            self.resuming = true;
+        }
+        reason
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breakpoints_are_kept_sorted_and_unique() {
+        let mut debugger = Debugger::default();
+        assert!(debugger.add_breakpoint(3));
+        assert!(debugger.add_breakpoint(1));
+        assert!(!debugger.add_breakpoint(3));
+        assert_eq!(debugger.breakpoints(), &[1, 3]);
+
+        assert!(debugger.remove_breakpoint(1));
+        assert!(!debugger.remove_breakpoint(1));
+        assert_eq!(debugger.breakpoints(), &[3]);
+    }
+
+    #[test]
+    fn breakpoint_pauses_once_per_line() {
+        let mut debugger = Debugger::default();
+        debugger.add_breakpoint(2);
+        assert_eq!(debugger.check(1, 1), None);
+        assert_eq!(debugger.check(2, 1), Some(PauseReason::Breakpoint));
+        // Resuming runs the paused instruction and the rest of the line.
+        assert_eq!(debugger.check(2, 1), None);
+        assert_eq!(debugger.check(2, 1), None);
+        assert_eq!(debugger.check(3, 1), None);
+    }
+
+    #[test]
+    fn step_stops_on_each_new_line() {
+        let mut debugger = Debugger::default();
+        debugger.set_mode(StepMode::Step);
+        assert_eq!(debugger.check(1, 1), Some(PauseReason::Step));
+        assert_eq!(debugger.check(1, 1), None);
+        assert_eq!(debugger.check(1, 1), None);
+        assert_eq!(debugger.check(2, 1), Some(PauseReason::Step));
+        assert_eq!(debugger.check(2, 1), None);
+        assert_eq!(debugger.check(3, 2), Some(PauseReason::Step));
+    }
+
+    #[test]
+    fn next_and_finish_respect_frames() {
+        let mut debugger = Debugger::default();
+        debugger.set_mode(StepMode::Next);
+        assert_eq!(debugger.check(1, 1), Some(PauseReason::Step));
+        assert_eq!(debugger.check(1, 1), None);
+        // A call on the same line runs to completion.
+        assert_eq!(debugger.check(5, 2), None);
+        assert_eq!(debugger.check(6, 2), None);
+        assert_eq!(debugger.check(2, 1), Some(PauseReason::Step));
+
+        debugger.set_mode(StepMode::Step);
+        assert_eq!(debugger.check(2, 1), None);
+        assert_eq!(debugger.check(5, 2), Some(PauseReason::Step));
+
+        debugger.set_mode(StepMode::Finish);
+        assert_eq!(debugger.check(5, 2), None);
+        assert_eq!(debugger.check(6, 2), None);
+        assert_eq!(debugger.check(2, 1), Some(PauseReason::Step));
+    }
+}
@@ -2,6 +2,8 @@ use std::io::{self, Write};
 
 use super::chunk::Chunk;
 use super::opcode::{OpCode, UnknownOpcode};
+use crate::array::Array;
+use crate::json::Json;
 use thiserror::Error;
 
 #[allow(dead_code)]
@@ -23,6 +25,11 @@ impl<'a> Disassembler<'a> {
         while offset < self.chunk.codes.length() {
             offset = self.disassemble_instruction(w, offset)?;
         }
+        self.write_footer(w)
+    }
+
+    fn write_footer<W: Write>(&self, w: &mut W) -> Result<(), DisassembleError> {
+        writeln!(w, "max stack depth: {}", self.chunk.max_stack_depth)?;
         Ok(())
     }
 
@@ -48,7 +55,7 @@ impl<'a> Disassembler<'a> {
             OpCode::Constant => {
                 let constant_index = self.chunk.codes[offset + 1];
                 let constant_value = &self.chunk.constants[constant_index as usize];
-                writeln!(w, "{opcode_enum:-16} {constant_index:4} '{constant_value}'")?;
+                writeln!(w, "{opcode_enum:<16} {constant_index:4} '{constant_value}'")?;
                 Ok(offset + 2)
             }
             opcode => {
@@ -57,6 +64,92 @@ impl<'a> Disassembler<'a> {
             }
         }
     }
+
+    /// Writes the chunk as a JSON array with one object per instruction, so
+    /// bytecode can be diffed mechanically between compiler versions.
+    pub fn write_json<W: Write>(&self, w: &mut W) -> Result<(), DisassembleError> {
+        let mut instructions = Array::default();
+
+        let mut offset = 0;
+        while offset < self.chunk.codes.length() {
+            let opcode = OpCode::try_from(self.chunk.codes[offset])?;
+            let operand_bytes = self
+                .chunk
+                .codes
+                .get(offset + 1..offset + 1 + opcode.operand_len())
+                .unwrap_or_default();
+
+            let mut operands = Array::default();
+            for &operand in operand_bytes {
+                operands.push(Json::from(usize::from(operand)));
+            }
+            let constant = match (opcode, operand_bytes) {
+                (OpCode::Constant, &[index]) => self
+                    .chunk
+                    .constants
+                    .get(usize::from(index))
+                    .map(|&value| f64::from(value)),
+                _ => None,
+            };
+
+            instructions.push(Json::object([
+                ("offset", offset.into()),
+                ("line", self.chunk.lines[offset].into()),
+                ("opcode", Json::string(opcode.to_string())),
+                ("operands", Json::Array(operands)),
+                ("constant", constant.into()),
+            ]));
+            offset += 1 + opcode.operand_len();
+        }
+
+        writeln!(w, "{}", Json::Array(instructions))?;
+        Ok(())
+    }
+
+    /// Writes each line of `source` followed by the instructions generated
+    /// from it. Lines that produced no code are still shown for context.
+    pub fn write_annotated<W: Write>(
+        &self,
+        w: &mut W,
+        source: &str,
+    ) -> Result<(), DisassembleError> {
+        writeln!(w, "== {} ==", self.name)?;
+
+        let source_lines: Array<&str> = {
+            let mut lines = Array::default();
+            source.lines().for_each(|line| lines.push(line));
+            lines
+        };
+        let write_source_line = |w: &mut W, line: usize| -> io::Result<()> {
+            let text = source_lines
+                .get(line.wrapping_sub(1))
+                .copied()
+                .unwrap_or_default();
+            writeln!(w, "{line:4}: {text}")
+        };
+
+        let mut last_line = 0;
+        let mut offset = 0;
+        while offset < self.chunk.codes.length() {
+            let line = self.chunk.lines[offset];
+            if line > last_line {
+                for skipped in last_line + 1..=line {
+                    write_source_line(w, skipped)?;
+                }
+            } else if line < last_line {
+                // Code can move backwards, e.g. the implicit return of a
+                // trailing expression; show the line it belongs to again.
+                write_source_line(w, line)?;
+            }
+            last_line = line;
+            offset = self.disassemble_instruction(w, offset)?;
+        }
+
+        for remaining in last_line + 1..=source_lines.length() {
+            write_source_line(w, remaining)?;
+        }
+        self.write_footer(w)
+    }
 }
 
 #[allow(dead_code)]
@@ -71,7 +164,6 @@ pub enum DisassembleError {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::array::Array;
     use crate::vm::opcode::OpCode;
 
     #[test]
@@ -144,5 +236,52 @@ mod tests {
         let line = lines.next().unwrap();
         assert!(line.contains("0003"));
         assert!(line.contains("2 OP_RETURN"));
+
+        assert_eq!(lines.next(), Some("max stack depth: 1"));
+    }
+
+    #[test]
+    fn test_disassemble_json() {
+        let mut chunk = Chunk::default();
+        let idx = chunk.write_constant(1.5.into()).unwrap();
+        chunk.write_opcode(OpCode::Constant, 1);
+        chunk.write_byte(idx, 1);
+        chunk.write_opcode(OpCode::Return, 2);
+
+        let disassembler = Disassembler::new(&chunk, "json");
+        let mut buffer = Array::default();
+        disassembler.write_json(&mut buffer).unwrap();
+
+        let output = std::str::from_utf8(&buffer).unwrap();
+        assert_eq!(
+            output.trim(),
+            concat!(
+                r#"[{"offset":0,"line":1,"opcode":"OP_CONSTANT","operands":[0],"constant":1.5},"#,
+                r#"{"offset":2,"line":2,"opcode":"OP_RETURN","operands":[],"constant":null}]"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_disassemble_annotated() {
+        let source = "// leading comment\n1 +\n2";
+        let chunk = crate::compiler::compile(source).unwrap();
+
+        let disassembler = Disassembler::new(&chunk, "annotated");
+        let mut buffer = Array::default();
+        disassembler.write_annotated(&mut buffer, source).unwrap();
+
+        let output = std::str::from_utf8(&buffer).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "== annotated ==");
+        assert_eq!(lines[1], "   1: // leading comment");
+        assert_eq!(lines[2], "   2: 1 +");
+        assert!(lines[3].contains("OP_CONSTANT"));
+        assert_eq!(lines[4], "   3: 2");
+        assert!(lines[5].contains("OP_CONSTANT"));
+        assert!(lines[6].contains("OP_ADD"));
+        assert!(lines[7].contains("OP_RETURN"));
+        assert_eq!(lines[8], "max stack depth: 2");
+        assert_eq!(lines.len(), 9);
     }
 }
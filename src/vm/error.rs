@@ -1,9 +1,16 @@
+use std::fmt;
+
 use thiserror::Error;
 
 use crate::array::Array;
-use crate::compiler::ParseError;
 use crate::compiler::scanner::ScanError;
+use crate::compiler::{
+    Diagnostic, ErrorCode, ErrorFormat, ParseError, Severity, format_parse_errors,
+};
+use crate::vm::disassembler::DisassembleError;
 use crate::vm::opcode::UnknownOpcode;
+use crate::vm::serialize::{DeserializeError, SerializeError};
+use crate::vm::verifier::VerifyError;
 
 #[derive(Debug, Error)]
 pub enum RoxError {
@@ -15,6 +22,9 @@ pub enum RoxError {
 
     #[error(transparent)]
     Runtime(#[from] RuntimeError),
+
+    #[error("{0}")]
+    Usage(String),
 }
 
 #[derive(Debug, Error)]
@@ -30,24 +40,139 @@ pub enum CompileError {
 
     #[error(transparent)]
     Parse(#[from] ParseErrorReport),
+
+    #[error(transparent)]
+    Verify(#[from] VerifyError),
+
+    #[error(transparent)]
+    Bytecode(#[from] DeserializeError),
+
+    #[error(transparent)]
+    Serialize(SerializeError),
+
+    #[error("{count} warning(s) treated as errors.")]
+    Warnings { count: usize },
 }
 
 #[derive(Debug, Error)]
-#[error("Parse error(s):\n{}", format_parse_errors(.0))]
+#[error(
+    "Parse error(s):\n{}",
+    format_parse_errors(.0, ErrorFormat::Human, "")
+)]
 pub struct ParseErrorReport(pub Array<ParseError>);
 
-fn format_parse_errors(errors: &Array<ParseError>) -> String {
-    errors.iter().fold(String::new(), |mut output, error| {
-        if !output.is_empty() {
-            output.push('\n');
+#[derive(Debug)]
+pub enum RuntimeError {
+    StackOverflow { max: usize, line: usize },
+    StackUnderflow { line: usize },
+    FuelExhausted { line: usize },
+    OutOfMemory { limit: usize, line: usize },
+    Timeout { line: usize },
+    Interrupted { line: usize },
+    NotSuspended,
+}
+
+impl CompileError {
+    /// The code of the error, unless it is an I/O error or a report of
+    /// parse errors, which carry their own codes.
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            CompileError::UnknownOpcode(_) => Some(ErrorCode::InvalidBytecode),
+            CompileError::Scan(e) => Some(e.code),
+            CompileError::Verify(_) | CompileError::Bytecode(DeserializeError::Verify(_)) => {
+                Some(ErrorCode::UnverifiedBytecode)
+            }
+            CompileError::Bytecode(_) => Some(ErrorCode::InvalidBytecode),
+            CompileError::Warnings { .. } => Some(ErrorCode::WarningsAsErrors),
+            CompileError::Io(_) | CompileError::Parse(_) | CompileError::Serialize(_) => None,
         }
-        output.push_str(&error.to_string());
-        output
-    })
+    }
 }
 
-#[derive(Debug, Error)]
-pub enum RuntimeError {}
+impl RuntimeError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            RuntimeError::StackOverflow { .. } => ErrorCode::StackOverflow,
+            RuntimeError::StackUnderflow { .. } => ErrorCode::StackUnderflow,
+            RuntimeError::FuelExhausted { .. } => ErrorCode::FuelExhausted,
+            RuntimeError::OutOfMemory { .. } => ErrorCode::OutOfMemory,
+            RuntimeError::Timeout { .. } => ErrorCode::Timeout,
+            RuntimeError::Interrupted { .. } => ErrorCode::Interrupted,
+            RuntimeError::NotSuspended => ErrorCode::NotSuspended,
+        }
+    }
+
+    /// The message, with the limit that was hit.
+    pub fn message(&self) -> String {
+        match self {
+            RuntimeError::StackOverflow { max, .. } => {
+                format!("Stack overflow (maximum stack size is {max}).")
+            }
+            RuntimeError::OutOfMemory { limit, .. } => {
+                format!("Out of memory (limit is {limit} bytes).")
+            }
+            other => other.code().message().to_owned(),
+        }
+    }
+
+    /// The line the script stopped at, if it had started.
+    pub fn line(&self) -> Option<usize> {
+        match *self {
+            RuntimeError::StackOverflow { line, .. }
+            | RuntimeError::StackUnderflow { line }
+            | RuntimeError::FuelExhausted { line }
+            | RuntimeError::OutOfMemory { line, .. }
+            | RuntimeError::Timeout { line }
+            | RuntimeError::Interrupted { line } => Some(line),
+            RuntimeError::NotSuspended => None,
+        }
+    }
+
+    /// The error with its stack trace. Chunks only record lines, so runtime
+    /// errors have no column.
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic {
+            code: Some(self.code().name().to_owned()),
+            line: self.line(),
+            trace: self
+                .line()
+                .map(|line| ("script".to_owned(), line))
+                .into_iter()
+                .collect(),
+            ..Diagnostic::new(Severity::Error, self.message())
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]", self.message(), self.code())?;
+        match self.line() {
+            Some(line) => write!(f, "\n[line {line}] in script"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+impl From<DisassembleError> for RoxError {
+    fn from(err: DisassembleError) -> Self {
+        match err {
+            DisassembleError::Io(e) => RoxError::Io(e),
+            DisassembleError::UnknownOpcode(e) => RoxError::Compile(CompileError::UnknownOpcode(e)),
+        }
+    }
+}
+
+impl From<SerializeError> for RoxError {
+    fn from(err: SerializeError) -> Self {
+        match err {
+            SerializeError::Io(e) => RoxError::Io(e),
+            other => RoxError::Compile(CompileError::Serialize(other)),
+        }
+    }
+}
 
 impl From<crate::compiler::CompileError> for RoxError {
     fn from(err: crate::compiler::CompileError) -> Self {
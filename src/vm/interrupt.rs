@@ -0,0 +1,52 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Asks a running [`Vm`](super::Vm) to stop at its next safe point.
+///
+/// Handles are cheap to clone and can be sent to other threads or used from
+/// a signal handler. An interrupt stops one run: the VM clears the request
+/// when it acts on it, and a host can drop a stale one with
+/// [`InterruptHandle::clear`].
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle {
+    requested: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    /// Requests that the current, or next, run stops.
+    pub fn interrupt(&self) {
+        self.requested.store(true, Ordering::Relaxed);
+    }
+
+    /// Drops a pending interrupt that no run has acted on.
+    pub fn clear(&self) {
+        self.requested.store(false, Ordering::Relaxed);
+    }
+
+    /// Whether an interrupt is pending, clearing it if so.
+    pub(super) fn take(&self) -> bool {
+        self.requested.swap(false, Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interrupt_is_seen_once() {
+        let handle = InterruptHandle::default();
+        let other = handle.clone();
+        assert!(!handle.take());
+
+        std::thread::spawn(move || other.interrupt())
+            .join()
+            .unwrap();
+        assert!(handle.take());
+        assert!(!handle.take());
+
+        handle.interrupt();
+        handle.clear();
+        assert!(!handle.take());
+    }
+}
@@ -0,0 +1,136 @@
+use std::time::{Duration, Instant};
+
+use super::interrupt::InterruptHandle;
+
+/// How often, in instructions, the dispatch loop looks at the clock and for
+/// interrupts. Without jumps or calls yet, this stands in for polling at
+/// backward jumps and calls.
+const CHECK_INTERVAL: u32 = 1024;
+
+/// Resource limits for running untrusted code. Every limit is off by default.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum number of instructions a single run may execute.
+    pub fuel: Option<u64>,
+    /// Maximum number of bytes the VM may allocate while running.
+    pub max_memory: Option<usize>,
+    /// Maximum wall-clock time a single run may take.
+    pub timeout: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Halt {
+    Fuel,
+    Timeout,
+    Interrupted,
+}
+
+/// What is left of the limits during one run. A suspended run keeps its
+/// budget, and the clock only runs between [`Budget::start`] and
+/// [`Budget::stop`].
+#[derive(Debug)]
+pub(super) struct Budget {
+    fuel: Option<u64>,
+    /// Running time left while stopped.
+    time_left: Option<Duration>,
+    deadline: Option<Instant>,
+    until_check: u32,
+}
+
+impl Budget {
+    pub(super) fn new(limits: &Limits) -> Self {
+        Self {
+            fuel: limits.fuel,
+            time_left: limits.timeout,
+            deadline: None,
+            until_check: 0,
+        }
+    }
+
+    /// Starts the clock, checking the deadline at the first instruction.
+    pub(super) fn start(&mut self) {
+        self.deadline = self.time_left.map(|left| Instant::now() + left);
+        self.until_check = 0;
+    }
+
+    /// Stops the clock, keeping the running time that is left.
+    pub(super) fn stop(&mut self) {
+        if let Some(deadline) = self.deadline.take() {
+            self.time_left = Some(deadline.saturating_duration_since(Instant::now()));
+        }
+    }
+
+    /// Accounts for one instruction about to be executed.
+    #[inline(always)]
+    pub(super) fn tick(&mut self, interrupt: &InterruptHandle) -> Result<(), Halt> {
+        if let Some(fuel) = &mut self.fuel {
+            *fuel = fuel.checked_sub(1).ok_or(Halt::Fuel)?;
+        }
+
+        if self.until_check == 0 {
+            if interrupt.take() {
+                return Err(Halt::Interrupted);
+            }
+            if self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                return Err(Halt::Timeout);
+            }
+            self.until_check = CHECK_INTERVAL;
+        }
+        self.until_check -= 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlimited_by_default() {
+        let interrupt = InterruptHandle::default();
+        let mut budget = Budget::new(&Limits::default());
+        for _ in 0..10_000 {
+            budget.tick(&interrupt).unwrap();
+        }
+    }
+
+    #[test]
+    fn fuel_runs_out() {
+        let interrupt = InterruptHandle::default();
+        let mut budget = Budget::new(&Limits {
+            fuel: Some(2),
+            ..Limits::default()
+        });
+        assert_eq!(budget.tick(&interrupt), Ok(()));
+        assert_eq!(budget.tick(&interrupt), Ok(()));
+        assert_eq!(budget.tick(&interrupt), Err(Halt::Fuel));
+    }
+
+    #[test]
+    fn deadline_is_checked_on_the_first_instruction() {
+        let interrupt = InterruptHandle::default();
+        let mut budget = Budget::new(&Limits {
+            timeout: Some(Duration::ZERO),
+            ..Limits::default()
+        });
+        budget.start();
+        assert_eq!(budget.tick(&interrupt), Err(Halt::Timeout));
+    }
+
+    #[test]
+    fn interrupts_are_polled_periodically() {
+        let interrupt = InterruptHandle::default();
+        let mut budget = Budget::new(&Limits::default());
+        budget.tick(&interrupt).unwrap();
+
+        interrupt.interrupt();
+        for _ in 1..CHECK_INTERVAL {
+            budget.tick(&interrupt).unwrap();
+        }
+        assert_eq!(budget.tick(&interrupt), Err(Halt::Interrupted));
+        assert_eq!(budget.tick(&interrupt), Ok(()));
+    }
+}
@@ -4,7 +4,7 @@ use thiserror::Error;
 
 #[allow(dead_code)]
 #[repr(u8)]
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OpCode {
     Return,
     Constant,
@@ -20,6 +20,44 @@ pub enum OpCode {
 #[error("Unknown opcode: {0}")]
 pub struct UnknownOpcode(u8);
 
+impl OpCode {
+    /// Decodes `byte` without checking that it names an opcode.
+    ///
+    /// # Safety
+    ///
+    /// `byte` must be a valid `OpCode` discriminant, e.g. because the chunk
+    /// it was read from has already been verified.
+    #[cfg_attr(debug_assertions, allow(dead_code))]
+    pub unsafe fn from_u8_unchecked(byte: u8) -> Self {
+        debug_assert!(Self::try_from(byte).is_ok(), "unknown opcode {byte}");
+        // SAFETY: `OpCode` is `repr(u8)` and the caller guarantees `byte` is in range.
+        unsafe { std::mem::transmute::<u8, Self>(byte) }
+    }
+
+    /// Number of operand bytes that follow the opcode in the code stream.
+    pub fn operand_len(self) -> usize {
+        match self {
+            Self::Constant => 1,
+            Self::Return
+            | Self::Negate
+            | Self::Add
+            | Self::Subtract
+            | Self::Multiply
+            | Self::Divide => 0,
+        }
+    }
+
+    /// How many values the instruction pops from and then pushes onto the stack.
+    pub fn stack_effect(self) -> (usize, usize) {
+        match self {
+            Self::Return => (1, 0),
+            Self::Constant => (0, 1),
+            Self::Negate => (1, 1),
+            Self::Add | Self::Subtract | Self::Multiply | Self::Divide => (2, 1),
+        }
+    }
+}
+
 impl TryFrom<u8> for OpCode {
     type Error = UnknownOpcode;
 
@@ -40,13 +78,13 @@ impl TryFrom<u8> for OpCode {
 impl fmt::Display for OpCode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Return => write!(f, "OP_RETURN"),
-            Self::Constant => write!(f, "OP_CONSTANT"),
-            Self::Negate => write!(f, "OP_NEGATE"),
-            Self::Add => write!(f, "OP_ADD"),
-            Self::Subtract => write!(f, "OP_SUBTRACT"),
-            Self::Multiply => write!(f, "OP_MULTIPLY"),
-            Self::Divide => write!(f, "OP_DIVIDE"),
+            Self::Return => f.pad("OP_RETURN"),
+            Self::Constant => f.pad("OP_CONSTANT"),
+            Self::Negate => f.pad("OP_NEGATE"),
+            Self::Add => f.pad("OP_ADD"),
+            Self::Subtract => f.pad("OP_SUBTRACT"),
+            Self::Multiply => f.pad("OP_MULTIPLY"),
+            Self::Divide => f.pad("OP_DIVIDE"),
         }
     }
 }
@@ -86,5 +124,12 @@ mod tests {
                 _ => prop_assert!(OpCode::try_from(byte).is_err()),
             }
         }
+
+        #[test]
+        fn prop_unchecked_matches_checked(byte in 0u8..=6) {
+            let checked = OpCode::try_from(byte).unwrap();
+            let unchecked = unsafe { OpCode::from_u8_unchecked(byte) };
+            prop_assert_eq!(checked, unchecked);
+        }
     }
 }
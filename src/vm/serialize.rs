@@ -0,0 +1,337 @@
+//! The `.roxc` binary bytecode format.
+//!
+//! All integers are little endian. A file is laid out as:
+//!
+//! ```text
+//! magic        b"ROXC"
+//! version      u16
+//! code         u32 length, then the raw code bytes
+//! lines        u32 run count, then (u32 line, u32 run length) pairs
+//! constants    u32 count, then one tagged constant each
+//! ```
+//!
+//! Constants start with a one byte tag describing the payload that follows,
+//! so new value types can be added without breaking older files. Only
+//! numbers exist so far; tag 1 is reserved for strings and tag 2 for
+//! functions, and both are rejected as unknown until the VM has them.
+
+use std::io::{self, Write};
+
+use thiserror::Error;
+
+use crate::array::Array;
+
+use super::chunk::Chunk;
+use super::value::Value;
+use super::verifier::VerifyError;
+
+pub const MAGIC: &[u8; 4] = b"ROXC";
+pub const FORMAT_VERSION: u16 = 1;
+
+/// A 64-bit IEEE 754 number, stored as its bit pattern.
+const TAG_NUMBER: u8 = 0;
+
+#[derive(Debug, Error)]
+pub enum SerializeError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error("Chunk section is too large for the bytecode format.")]
+    TooLarge,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DeserializeError {
+    #[error("Not a rox bytecode file.")]
+    BadMagic,
+
+    #[error("Unsupported bytecode format version {0} (expected {FORMAT_VERSION}).")]
+    UnsupportedVersion(u16),
+
+    #[error("Unexpected end of bytecode file.")]
+    Truncated,
+
+    #[error("Unknown constant tag {0}.")]
+    UnknownConstantTag(u8),
+
+    #[error("Line table covers {lines} bytes but the code has {codes}.")]
+    LineTableMismatch { codes: usize, lines: usize },
+
+    #[error("Trailing bytes after the end of the chunk.")]
+    TrailingBytes,
+
+    #[error(transparent)]
+    Verify(#[from] VerifyError),
+}
+
+impl Chunk {
+    pub fn serialize<W: Write>(&self, w: &mut W) -> Result<(), SerializeError> {
+        w.write_all(MAGIC)?;
+        w.write_all(&FORMAT_VERSION.to_le_bytes())?;
+
+        write_len(w, self.codes.length())?;
+        w.write_all(&self.codes)?;
+
+        // Lines are stored run-length encoded, as consecutive instructions
+        // almost always come from the same line.
+        let mut runs: Array<(usize, usize)> = Array::default();
+        for &line in self.lines.iter() {
+            match runs.last_mut() {
+                Some((last, count)) if *last == line => *count += 1,
+                _ => runs.push((line, 1)),
+            }
+        }
+        write_len(w, runs.length())?;
+        for &(line, count) in runs.iter() {
+            write_len(w, line)?;
+            write_len(w, count)?;
+        }
+
+        write_len(w, self.constants.length())?;
+        for &constant in self.constants.iter() {
+            w.write_all(&[TAG_NUMBER])?;
+            w.write_all(&f64::from(constant).to_bits().to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Reads a chunk back from its `.roxc` representation.
+    ///
+    /// The result is not verified; use [`Chunk::load`] for untrusted input.
+    pub fn deserialize(bytes: &[u8]) -> Result<Chunk, DeserializeError> {
+        let mut reader = Reader { bytes };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(DeserializeError::BadMagic);
+        }
+        let version = u16::from_le_bytes(reader.array()?);
+        if version != FORMAT_VERSION {
+            return Err(DeserializeError::UnsupportedVersion(version));
+        }
+
+        let mut chunk = Chunk::default();
+
+        let code_len = reader.len()?;
+        for &byte in reader.take(code_len)? {
+            chunk.codes.push(byte);
+        }
+
+        // Run lengths are untrusted, so check them against the code before
+        // allocating any lines.
+        let run_count = reader.len()?;
+        let mut total = 0usize;
+        for _ in 0..run_count {
+            let line = reader.len()?;
+            let count = reader.len()?;
+            total = match total.checked_add(count) {
+                Some(total) if total <= code_len => total,
+                total => {
+                    return Err(DeserializeError::LineTableMismatch {
+                        codes: code_len,
+                        lines: total.unwrap_or(usize::MAX),
+                    });
+                }
+            };
+            for _ in 0..count {
+                chunk.lines.push(line);
+            }
+        }
+        if chunk.lines.length() != chunk.codes.length() {
+            return Err(DeserializeError::LineTableMismatch {
+                codes: chunk.codes.length(),
+                lines: chunk.lines.length(),
+            });
+        }
+
+        let constant_count = reader.len()?;
+        for _ in 0..constant_count {
+            let value = match reader.array::<1>()?[0] {
+                TAG_NUMBER => Value::from(f64::from_bits(u64::from_le_bytes(reader.array()?))),
+                tag => return Err(DeserializeError::UnknownConstantTag(tag)),
+            };
+            chunk.constants.push(value);
+        }
+
+        if !reader.bytes.is_empty() {
+            return Err(DeserializeError::TrailingBytes);
+        }
+        Ok(chunk)
+    }
+
+    /// Deserializes and verifies a chunk so it is safe to run.
+    pub fn load(bytes: &[u8]) -> Result<Chunk, DeserializeError> {
+        let mut chunk = Self::deserialize(bytes)?;
+        // The depth is not stored in the file, so recompute it from the code.
+        chunk.max_stack_depth = chunk.stack_depth()?;
+        chunk.verify()?;
+        Ok(chunk)
+    }
+}
+
+/// Whether `bytes` looks like a `.roxc` file rather than Lox source.
+pub fn is_bytecode(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+fn write_len<W: Write>(w: &mut W, len: usize) -> Result<(), SerializeError> {
+    let len = u32::try_from(len).map_err(|_| SerializeError::TooLarge)?;
+    w.write_all(&len.to_le_bytes())?;
+    Ok(())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DeserializeError> {
+        if self.bytes.len() < n {
+            return Err(DeserializeError::Truncated);
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DeserializeError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn len(&mut self) -> Result<usize, DeserializeError> {
+        Ok(u32::from_le_bytes(self.array()?) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler;
+    use crate::compiler::tests::expression;
+    use crate::vm::disassembler::Disassembler;
+    use crate::vm::opcode::OpCode;
+    use proptest::prelude::*;
+
+    fn serialize(chunk: &Chunk) -> Array<u8> {
+        let mut buffer = Array::default();
+        chunk.serialize(&mut buffer).unwrap();
+        buffer
+    }
+
+    fn disassemble(chunk: &Chunk) -> String {
+        let mut buffer = Array::default();
+        Disassembler::new(chunk, "chunk")
+            .write(&mut buffer)
+            .unwrap();
+        std::str::from_utf8(&buffer).unwrap().to_owned()
+    }
+
+    #[test]
+    fn header_is_checked() {
+        assert_eq!(
+            Chunk::deserialize(b"NOPE\x01\x00").unwrap_err(),
+            DeserializeError::BadMagic
+        );
+        assert_eq!(
+            Chunk::deserialize(b"ROXC\x09\x00").unwrap_err(),
+            DeserializeError::UnsupportedVersion(9)
+        );
+        assert_eq!(
+            Chunk::deserialize(b"ROXC\x01").unwrap_err(),
+            DeserializeError::Truncated
+        );
+    }
+
+    #[test]
+    fn lines_are_run_length_encoded() {
+        let mut chunk = Chunk::default();
+        for _ in 0..10 {
+            chunk.write_opcode(OpCode::Negate, 7);
+        }
+        chunk.write_opcode(OpCode::Return, 8);
+
+        // header + code + 2 runs + empty constant pool
+        let expected = 6 + (4 + 11) + (4 + 2 * 8) + 4;
+        assert_eq!(serialize(&chunk).length(), expected);
+    }
+
+    #[test]
+    fn line_table_must_match_the_code() {
+        // Empty code with one run claiming u32::MAX lines.
+        let mut bytes = b"ROXC\x01\x00".to_vec();
+        for word in [0u32, 1, 1, u32::MAX, 0] {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        assert_eq!(
+            Chunk::deserialize(&bytes).unwrap_err(),
+            DeserializeError::LineTableMismatch {
+                codes: 0,
+                lines: u32::MAX as usize
+            }
+        );
+
+        // A line table that stops short of the code.
+        let mut chunk = Chunk::default();
+        chunk.write_opcode(OpCode::Return, 1);
+        chunk.lines.clear();
+        assert_eq!(
+            Chunk::deserialize(&serialize(&chunk)).unwrap_err(),
+            DeserializeError::LineTableMismatch { codes: 1, lines: 0 }
+        );
+    }
+
+    #[test]
+    fn unknown_constant_tag() {
+        let mut bytes = serialize(&compiler::compile("1").unwrap());
+        let tag_offset = bytes.length() - 9;
+        *bytes.get_mut(tag_offset).unwrap() = 0xee;
+        assert_eq!(
+            Chunk::deserialize(&bytes).unwrap_err(),
+            DeserializeError::UnknownConstantTag(0xee)
+        );
+    }
+
+    #[test]
+    fn load_verifies() {
+        let mut chunk = Chunk::default();
+        chunk.write_opcode(OpCode::Add, 1);
+        chunk.write_opcode(OpCode::Return, 1);
+        assert!(matches!(
+            Chunk::load(&serialize(&chunk)),
+            Err(DeserializeError::Verify(VerifyError::StackUnderflow { .. }))
+        ));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_compiled_round_trip_disassembles_identically(source in expression()) {
+            let chunk = compiler::compile(&source).unwrap();
+            let loaded = Chunk::load(&serialize(&chunk)).unwrap();
+            prop_assert_eq!(disassemble(&chunk), disassemble(&loaded));
+        }
+
+        #[test]
+        fn prop_raw_round_trip(
+            codes in prop::collection::vec(any::<u8>(), 0..200),
+            line_steps in prop::collection::vec(0usize..3, 200),
+            constants in prop::collection::vec(any::<f64>(), 0..50),
+        ) {
+            let mut chunk = Chunk::default();
+            let mut line = 1;
+            for (&byte, step) in codes.iter().zip(line_steps) {
+                line += step;
+                chunk.write_byte(byte, line);
+            }
+            for &constant in &constants {
+                chunk.write_constant(constant.into()).unwrap();
+            }
+
+            let loaded = Chunk::deserialize(&serialize(&chunk)).unwrap();
+            prop_assert_eq!(&*loaded.codes, &*chunk.codes);
+            prop_assert_eq!(&*loaded.lines, &*chunk.lines);
+            let bits = |c: &Chunk| c.constants.iter().map(|&v| f64::from(v).to_bits()).collect::<Vec<_>>();
+            prop_assert_eq!(bits(&loaded), bits(&chunk));
+        }
+    }
+}
@@ -1,22 +1,32 @@
 use std::fmt;
 
 use super::value::Value;
+use crate::array::Array;
 
-const STACK_MAX: usize = 256;
+/// Default maximum number of values on the stack.
+pub(super) const STACK_MAX: usize = 256;
 
-#[allow(dead_code)]
 #[derive(Debug)]
 pub(super) struct ValueStack {
-    slots: [Value; STACK_MAX],
-    top: usize,
+    slots: Array<Value>,
+    max_size: usize,
+    /// Limit on the bytes the backing storage may occupy.
+    max_bytes: Option<usize>,
+    /// Whether pushes must check the limits, see [`ValueStack::prepare`].
+    checked: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum StackError {
+    Overflow { max: usize },
+    Underflow,
+    OutOfMemory { limit: usize },
 }
 
 impl fmt::Display for ValueStack {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "[")?;
-        self.slots[..self.top]
-            .iter()
-            .try_for_each(|v| write!(f, " {v}"))?;
+        self.slots.iter().try_for_each(|v| write!(f, " {v}"))?;
         write!(f, " ]")
     }
 }
@@ -24,23 +34,135 @@ impl fmt::Display for ValueStack {
 impl Default for ValueStack {
     fn default() -> Self {
         Self {
-            slots: [0.0.into(); STACK_MAX],
-            top: 0,
+            slots: Array::default(),
+            max_size: STACK_MAX,
+            max_bytes: None,
+            checked: true,
         }
     }
 }
 
-#[allow(dead_code)]
 impl ValueStack {
-    pub(super) fn push(&mut self, value: Value) {
-        debug_assert!(self.top < STACK_MAX, "stack overflow");
-        self.slots[self.top] = value;
-        self.top += 1;
+    /// Limits how many values the stack may hold. The backing storage grows
+    /// on demand up to this size.
+    pub(super) fn set_max_size(&mut self, max_size: usize) {
+        self.max_size = max_size;
+    }
+
+    /// Gets ready to run code that needs at most `depth` more slots.
+    ///
+    /// If they fit under the size limit they are allocated up front and
+    /// pushes skip the overflow check. Otherwise pushes stay checked so the
+    /// overflow is reported at the instruction that causes it. A memory
+    /// limit always keeps pushes checked, as growth must be accounted for.
+    pub(super) fn prepare(&mut self, depth: usize) {
+        self.checked =
+            self.max_bytes.is_some() || self.slots.length().saturating_add(depth) > self.max_size;
+        if !self.checked {
+            self.slots.reserve(depth);
+        }
+    }
+
+    /// Limits the memory the stack may allocate for its values.
+    pub(super) fn set_max_bytes(&mut self, max_bytes: Option<usize>) {
+        self.max_bytes = max_bytes;
+    }
+
+    pub(super) fn reset(&mut self) {
+        self.slots.clear();
+        self.checked = true;
+    }
+
+    pub(super) fn push(&mut self, value: Value) -> Result<(), StackError> {
+        if self.checked {
+            if self.slots.length() >= self.max_size {
+                return Err(StackError::Overflow { max: self.max_size });
+            }
+            if let Some(limit) = self.max_bytes
+                && self.slots.length() == self.slots.capacity()
+                && self.slots.grown_capacity() * size_of::<Value>() > limit
+            {
+                return Err(StackError::OutOfMemory { limit });
+            }
+            self.slots.push(value);
+        } else {
+            // SAFETY: `prepare` reserved room for every push the chunk makes.
+            unsafe { self.slots.push_unchecked(value) };
+        }
+        Ok(())
+    }
+
+    pub(super) fn pop(&mut self) -> Result<Value, StackError> {
+        self.slots.pop().ok_or(StackError::Underflow)
+    }
+
+    /// How many values the backing storage has room for.
+    pub(super) fn capacity(&self) -> usize {
+        self.slots.capacity()
+    }
+
+    /// The values from the bottom of the stack to the top.
+    pub(super) fn values(&self) -> &[Value] {
+        &self.slots
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_up_to_max_size() {
+        let mut stack = ValueStack::default();
+        stack.set_max_size(1000);
+        for i in 0..1000 {
+            stack.push((i as f64).into()).unwrap();
+        }
+        assert_eq!(
+            stack.push(0.0.into()),
+            Err(StackError::Overflow { max: 1000 })
+        );
+        assert_eq!(stack.pop(), Ok(999.0.into()));
+    }
+
+    #[test]
+    fn prepare_only_skips_checks_within_limit() {
+        let mut stack = ValueStack::default();
+        stack.set_max_size(2);
+        stack.prepare(3);
+        assert!(stack.checked);
+        stack.prepare(2);
+        assert!(!stack.checked);
+        stack.push(1.0.into()).unwrap();
+        stack.push(2.0.into()).unwrap();
+        stack.reset();
+        assert!(stack.checked);
+    }
+
+    #[test]
+    fn growth_respects_memory_limit() {
+        let mut stack = ValueStack::default();
+        stack.set_max_bytes(Some(4 * size_of::<Value>()));
+        for i in 0..4 {
+            stack.push((i as f64).into()).unwrap();
+        }
+        assert_eq!(
+            stack.push(0.0.into()),
+            Err(StackError::OutOfMemory {
+                limit: 4 * size_of::<Value>()
+            })
+        );
     }
 
-    pub(super) fn pop(&mut self) -> Value {
-        debug_assert!(self.top > 0, "stack underflow");
-        self.top -= 1;
-        self.slots[self.top]
+    #[test]
+    fn underflow_and_reset() {
+        let mut stack = ValueStack::default();
+        assert_eq!(stack.pop(), Err(StackError::Underflow));
+        stack.push(1.0.into()).unwrap();
+        stack.push(2.0.into()).unwrap();
+        assert_eq!(stack.to_string(), "[ 1 2 ]");
+        stack.reset();
+        assert_eq!(stack.to_string(), "[ ]");
+        assert_eq!(stack.pop(), Err(StackError::Underflow));
     }
 }
@@ -0,0 +1,214 @@
+use thiserror::Error;
+
+use super::chunk::Chunk;
+use super::opcode::OpCode;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum VerifyError {
+    #[error("Line table has {lines} entries for {codes} bytes of code.")]
+    LineTableMismatch { codes: usize, lines: usize },
+
+    #[error("Unknown opcode {byte} at offset {offset}.")]
+    UnknownOpcode { offset: usize, byte: u8 },
+
+    #[error("Truncated {opcode} at offset {offset}.")]
+    TruncatedOperand { offset: usize, opcode: OpCode },
+
+    #[error("Constant index {index} at offset {offset} is out of range ({count} constants).")]
+    ConstantOutOfRange {
+        offset: usize,
+        index: usize,
+        count: usize,
+    },
+
+    #[error("{opcode} at offset {offset} needs {needed} value(s) but the stack holds {depth}.")]
+    StackUnderflow {
+        offset: usize,
+        opcode: OpCode,
+        needed: usize,
+        depth: usize,
+    },
+
+    #[error("Chunk declares a maximum stack depth of {declared} but needs {actual}.")]
+    StackDepthTooSmall { declared: usize, actual: usize },
+
+    #[error("Execution runs off the end of the chunk.")]
+    MissingReturn,
+}
+
+impl Chunk {
+    /// Checks that the chunk is safe to hand to the VM and returns the
+    /// maximum operand stack depth it can reach.
+    ///
+    /// Every instruction is decoded and its operands bounds checked, then the
+    /// stack effect of each opcode is interpreted abstractly to track the
+    /// stack depth along the (currently straight-line) control flow. The
+    /// chunk's declared `max_stack_depth` must cover what it actually needs.
+    pub fn verify(&self) -> Result<usize, VerifyError> {
+        let actual = self.stack_depth()?;
+        if self.max_stack_depth < actual {
+            return Err(VerifyError::StackDepthTooSmall {
+                declared: self.max_stack_depth,
+                actual,
+            });
+        }
+        Ok(actual)
+    }
+
+    /// Computes the maximum stack depth by abstract interpretation.
+    pub fn stack_depth(&self) -> Result<usize, VerifyError> {
+        if self.codes.length() != self.lines.length() {
+            return Err(VerifyError::LineTableMismatch {
+                codes: self.codes.length(),
+                lines: self.lines.length(),
+            });
+        }
+
+        let mut offset = 0;
+        let mut depth = 0usize;
+        let mut max_depth = 0usize;
+
+        while offset < self.codes.length() {
+            let byte = self.codes[offset];
+            let opcode =
+                OpCode::try_from(byte).map_err(|_| VerifyError::UnknownOpcode { offset, byte })?;
+
+            let operands = self
+                .codes
+                .get(offset + 1..offset + 1 + opcode.operand_len())
+                .ok_or(VerifyError::TruncatedOperand { offset, opcode })?;
+
+            if opcode == OpCode::Constant {
+                let index = usize::from(operands[0]);
+                if index >= self.constants.length() {
+                    return Err(VerifyError::ConstantOutOfRange {
+                        offset,
+                        index,
+                        count: self.constants.length(),
+                    });
+                }
+            }
+
+            let (pops, pushes) = opcode.stack_effect();
+            depth = depth.checked_sub(pops).ok_or(VerifyError::StackUnderflow {
+                offset,
+                opcode,
+                needed: pops,
+                depth,
+            })? + pushes;
+            max_depth = max_depth.max(depth);
+
+            if opcode == OpCode::Return {
+                // Nothing after a return is reachable without jumps.
+                return Ok(max_depth);
+            }
+            offset += 1 + opcode.operand_len();
+        }
+
+        Err(VerifyError::MissingReturn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler;
+
+    fn constant(chunk: &mut Chunk, value: f64) {
+        let idx = chunk.write_constant(value.into()).unwrap();
+        chunk.write_opcode(OpCode::Constant, 1);
+        chunk.write_byte(idx, 1);
+    }
+
+    #[test]
+    fn compiled_chunks_verify() {
+        let chunk = compiler::compile("1 + 2 * (3 - -4)").unwrap();
+        assert_eq!(chunk.verify(), Ok(4));
+    }
+
+    #[test]
+    fn empty_chunk_has_no_return() {
+        assert_eq!(Chunk::default().verify(), Err(VerifyError::MissingReturn));
+    }
+
+    #[test]
+    fn unknown_opcode() {
+        let mut chunk = Chunk::default();
+        chunk.write_byte(200, 1);
+        assert_eq!(
+            chunk.verify(),
+            Err(VerifyError::UnknownOpcode {
+                offset: 0,
+                byte: 200
+            })
+        );
+    }
+
+    #[test]
+    fn truncated_constant() {
+        let mut chunk = Chunk::default();
+        chunk.write_opcode(OpCode::Constant, 1);
+        assert_eq!(
+            chunk.verify(),
+            Err(VerifyError::TruncatedOperand {
+                offset: 0,
+                opcode: OpCode::Constant
+            })
+        );
+    }
+
+    #[test]
+    fn constant_out_of_range() {
+        let mut chunk = Chunk::default();
+        chunk.write_opcode(OpCode::Constant, 1);
+        chunk.write_byte(0, 1);
+        chunk.write_opcode(OpCode::Return, 1);
+        assert_eq!(
+            chunk.verify(),
+            Err(VerifyError::ConstantOutOfRange {
+                offset: 0,
+                index: 0,
+                count: 0
+            })
+        );
+    }
+
+    #[test]
+    fn stack_underflow() {
+        let mut chunk = Chunk::default();
+        constant(&mut chunk, 1.0);
+        chunk.write_opcode(OpCode::Add, 1);
+        chunk.write_opcode(OpCode::Return, 1);
+        assert_eq!(
+            chunk.verify(),
+            Err(VerifyError::StackUnderflow {
+                offset: 2,
+                opcode: OpCode::Add,
+                needed: 2,
+                depth: 1
+            })
+        );
+    }
+
+    #[test]
+    fn declared_depth_must_cover_actual_depth() {
+        let mut chunk = Chunk::default();
+        constant(&mut chunk, 1.0);
+        chunk.write_opcode(OpCode::Return, 1);
+        chunk.max_stack_depth = 0;
+        assert_eq!(
+            chunk.verify(),
+            Err(VerifyError::StackDepthTooSmall {
+                declared: 0,
+                actual: 1
+            })
+        );
+    }
+
+    #[test]
+    fn falls_off_the_end() {
+        let mut chunk = Chunk::default();
+        constant(&mut chunk, 1.0);
+        assert_eq!(chunk.verify(), Err(VerifyError::MissingReturn));
+    }
+}